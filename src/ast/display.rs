@@ -6,8 +6,8 @@ use std::{
 use thin_vec::ThinVec;
 
 use super::{
-    ArraySeg, ExprKind, Field, FnDecl, Identifier, Impl, MatchArm, Param, Pat, PatKind, Trait,
-    TyKind, TypeId,
+    ArraySeg, Binding, ExprKind, Field, FnDecl, Generic, Identifier, Impl, MatchArm, Param, Pat,
+    PatKind, Trait, TyKind, TypeId,
 };
 use crate::{
     ast::{Ast, BinaryOp, BlockId, ExprId, Lit, UnaryOp},
@@ -36,8 +36,10 @@ impl Writer<'_> {
     fn display_expr(&mut self, expr: ExprId) {
         let inside_expr = mem::replace(&mut self.inside_expr, true);
         match self.ast.exprs[expr].kind {
-            ExprKind::Impl(Impl { ref generics, ty, ref methods }) => {
-                ("impl", Generics(generics), " ", ty, methods).write(self);
+            ExprKind::Impl(Impl { ref generics, trait_, ty, ref methods, ref consts }) => {
+                let trait_ = trait_.map(|trait_| (trait_, " for "));
+                let items: ThinVec<_> = consts.iter().chain(methods).copied().collect();
+                ("impl", Generics(generics), " ", trait_, ty, &items).write(self);
             }
             ExprKind::Match { scrutinee, ref arms } => {
                 ("match ", scrutinee, " {").write(self);
@@ -48,9 +50,11 @@ impl Writer<'_> {
             }
             ExprKind::Unreachable => "unreachable".write(self),
             ExprKind::Assert(expr) => ("assert ", expr).write(self),
-            ExprKind::Struct { ident, ref fields, .. } => ("struct ", ident, fields).write(self),
-            ExprKind::Break => "break".write(self),
-            ExprKind::Continue => "continue".write(self),
+            ExprKind::Struct { ident, ref generics, ref fields } => {
+                ("struct ", ident, Generics(generics), fields).write(self);
+            }
+            ExprKind::Break(label) => ("break", label.map(|label| (" ", label))).write(self),
+            ExprKind::Continue(label) => ("continue", label.map(|label| (" ", label))).write(self),
             ExprKind::Return(expr) => ("return", expr.map(|expr| (" ", expr))).write(self),
             ExprKind::Lit(ref lit) => lit.write(self),
             ExprKind::Binary { lhs, op, rhs } => {
@@ -71,12 +75,15 @@ impl Writer<'_> {
             ExprKind::FieldAccess { expr, field, .. } => (expr, ".", field).write(self),
             ExprKind::Block(block) => self.display_block(block),
             ExprKind::FnDecl(ref decl) => decl.write(self),
+            ExprKind::Closure { ref params, body } => {
+                ("|", Sep(params, ", "), "| ", body).write(self);
+            }
             ExprKind::Trait(Trait { ident, ref methods }) => {
                 ("trait ", ident, methods).write(self);
             }
-            ExprKind::Let { ident, ty, expr } => {
+            ExprKind::Let { ref binding, ty, expr } => {
                 self.inside_expr = inside_expr;
-                ("let ", ident, ty.map(|ty| (": ", ty)), " = ").write(self);
+                ("let ", binding, ty.map(|ty| (": ", ty)), " = ").write(self);
                 self.inside_expr = false;
                 expr.write(self);
             }
@@ -86,12 +93,16 @@ impl Writer<'_> {
                 self.inside_expr = false;
                 expr.write(self);
             }
-            ExprKind::For { ident, iter, body } => {
-                ("for ", ident, " in ", iter, body).write(self);
+            ExprKind::For { ident, iter, body, label } => {
+                (label.map(|label| (label, ": ")), "for ", ident, " in ", iter, body).write(self);
             }
-            ExprKind::While { condition, block } => {
+            ExprKind::While { condition, block, label } => {
                 self.inside_expr = inside_expr;
-                ("while ", condition, block).write(self);
+                (label.map(|label| (label, ": ")), "while ", condition, block).write(self);
+            }
+            ExprKind::Loop { block, label } => {
+                self.inside_expr = inside_expr;
+                (label.map(|label| (label, ": ")), "loop ", block).write(self);
             }
             ExprKind::If { ref arms, els } => {
                 self.inside_expr = inside_expr;
@@ -186,7 +197,7 @@ impl Dump for ThinVec<Field> {
     }
 }
 
-struct Generics<'a>(&'a ThinVec<Identifier>);
+struct Generics<'a>(&'a ThinVec<Generic>);
 
 impl Dump for Generics<'_> {
     fn write(&self, w: &mut Writer) {
@@ -204,6 +215,9 @@ impl Dump for Lit {
             Self::FStr(segments) => FStr(segments).write(w),
             Self::Char(char) => _ = write!(w.f, "{char:?}"),
             Self::Array { segments } => ("[", Sep(segments, ", "), "]").write(w),
+            Self::Tuple(elems) => {
+                ("(", Sep(elems, ", "), (elems.len() == 1).then_some(","), ")").write(w);
+            }
         }
     }
 }
@@ -273,6 +287,9 @@ impl Dump for ThinVec<ExprId> {
         ("{", Line).write(w);
         for (index, decl) in self.iter().enumerate() {
             decl.write(w);
+            if let ExprKind::Const { .. } = w.ast.exprs[*decl].kind {
+                w.f.push(';');
+            }
             if index + 1 == self.len() {
                 w.indent -= 1;
             }
@@ -288,12 +305,27 @@ impl Dump for Param {
     }
 }
 
+impl Dump for Binding {
+    fn write(&self, w: &mut Writer) {
+        match self {
+            Self::Ident(ident) => ident.write(w),
+            Self::Tuple(elems) => ("(", Sep(elems, ", "), ")").write(w),
+        }
+    }
+}
+
 impl Dump for Field {
     fn write(&self, w: &mut Writer) {
         (self.ident, ": ", self.ty).write(w);
     }
 }
 
+impl Dump for Generic {
+    fn write(&self, w: &mut Writer) {
+        (self.ident, self.bound.map(|bound| (": ", bound))).write(w);
+    }
+}
+
 impl Dump for Identifier {
     fn write(&self, w: &mut Writer) {
         self.symbol.write(w);
@@ -303,13 +335,14 @@ impl Dump for Identifier {
 impl Dump for TypeId {
     fn write(&self, w: &mut Writer) {
         match w.ast.types[*self].kind {
-            TyKind::Ref(inner) => ("&", inner).write(w),
+            TyKind::Ref(inner, mutability) => (mutability.prefix(), inner).write(w),
             TyKind::Func { ref params, ret } => {
                 ("fn(", Sep(params, ", "), ")", ret.map(|ret| (" -> ", ret))).write(w);
             }
             TyKind::Never => w.f.push('!'),
             TyKind::Unit => w.f.push_str("()"),
             TyKind::Array(of) => ("[", of, "]").write(w),
+            TyKind::Tuple(ref elems) => ("(", Sep(elems, ", "), ")").write(w),
             TyKind::Name { ident, ref generics } => (ident, "<", Sep(generics, ", "), ">").write(w),
         }
     }
@@ -333,6 +366,7 @@ impl Dump for UnaryOp {
             Self::Not => "!",
             Self::Neg => "-",
             Self::Ref => "&",
+            Self::RefMut => "&mut ",
             Self::Deref => "*",
         });
     }
@@ -44,6 +44,12 @@ pub struct Identifier {
     pub span: Span,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct Generic {
+    pub ident: Identifier,
+    pub bound: Option<Identifier>,
+}
+
 #[derive(Debug)]
 pub struct Param {
     pub ident: Identifier,
@@ -68,8 +74,15 @@ pub enum TyKind {
     Unit,
     Name { ident: Symbol, generics: ThinVec<TypeId> },
     Array(TypeId),
+    Tuple(ThinVec<TypeId>),
     Func { params: ThinVec<TypeId>, ret: Option<TypeId> },
-    Ref(TypeId),
+    Ref(TypeId, Mutability),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Mutability {
+    Not,
+    Mut,
 }
 
 #[derive(Debug)]
@@ -84,20 +97,29 @@ pub enum ExprKind {
     FieldAccess { expr: ExprId, field: Identifier },
     Lit(Lit),
     Block(BlockId),
-    Let { ident: Identifier, ty: Option<TypeId>, expr: ExprId },
+    Let { binding: Binding, ty: Option<TypeId>, expr: ExprId },
     Const { ident: Identifier, ty: Option<TypeId>, expr: ExprId },
-    While { condition: ExprId, block: BlockId },
-    For { ident: Identifier, iter: ExprId, body: BlockId },
+    While { condition: ExprId, block: BlockId, label: Option<Identifier> },
+    Loop { block: BlockId, label: Option<Identifier> },
+    For { ident: Identifier, iter: ExprId, body: BlockId, label: Option<Identifier> },
     If { arms: ThinVec<IfStmt>, els: Option<BlockId> },
     Match { scrutinee: ExprId, arms: ThinVec<MatchArm> },
     Return(Option<ExprId>),
     Assert(ExprId),
-    Break,
-    Continue,
+    Break(Option<Identifier>),
+    Continue(Option<Identifier>),
     Trait(Trait),
     Impl(Impl),
     FnDecl(FnDecl),
-    Struct { ident: Identifier, generics: ThinVec<Identifier>, fields: ThinVec<Field> },
+    Closure { params: ThinVec<Param>, body: ExprId },
+    Struct { ident: Identifier, generics: ThinVec<Generic>, fields: ThinVec<Field> },
+}
+
+/// The left hand side of a `let`, tuples are destructured into their elements.
+#[derive(Debug, Clone)]
+pub enum Binding {
+    Ident(Identifier),
+    Tuple(ThinVec<Binding>),
 }
 
 #[derive(Debug)]
@@ -124,7 +146,7 @@ pub struct MatchArm {
 #[derive(Debug)]
 pub struct FnDecl {
     pub ident: Identifier,
-    pub generics: ThinVec<Identifier>,
+    pub generics: ThinVec<Generic>,
     pub params: ThinVec<Param>,
     pub ret: Option<TypeId>,
     pub block: Option<BlockId>,
@@ -138,9 +160,12 @@ pub struct Trait {
 
 #[derive(Debug)]
 pub struct Impl {
-    pub generics: ThinVec<Identifier>,
+    pub generics: ThinVec<Generic>,
+    pub trait_: Option<Identifier>,
     pub ty: TypeId,
     pub methods: ThinVec<ExprId>,
+    // associated constants, each an `ExprKind::Const`
+    pub consts: ThinVec<ExprId>,
 }
 
 #[derive(Debug)]
@@ -164,6 +189,7 @@ pub enum Lit {
     FStr(ThinVec<ExprId>),
     Char(char),
     Array { segments: ThinVec<ArraySeg> },
+    Tuple(ThinVec<ExprId>),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -206,6 +232,7 @@ pub enum UnaryOp {
     Neg,
     Not,
     Ref,
+    RefMut,
     Deref,
 }
 
@@ -221,6 +248,15 @@ impl Ast {
     }
 }
 
+impl Mutability {
+    pub const fn prefix(self) -> &'static str {
+        match self {
+            Self::Not => "&",
+            Self::Mut => "&mut ",
+        }
+    }
+}
+
 impl ExprKind {
     pub fn todo_span(self) -> Expr {
         self.with_span(Span::ZERO)
@@ -238,8 +274,9 @@ impl BinOpKind {
             Self::Mul | Self::MulAssign => "multiply",
             Self::Div | Self::DivAssign => "divide",
             Self::Mod | Self::ModAssign => "mod",
-            Self::Less | Self::LessEq | Self::Greater | Self::GreaterEq => "compare",
-            Self::Neq | Self::Eq => "s",
+            Self::Less | Self::LessEq | Self::Greater | Self::GreaterEq | Self::Neq | Self::Eq => {
+                "compare"
+            }
             Self::Assign => "assign",
             Self::Range | Self::RangeInclusive => "produce a range of",
             Self::And => "and",
@@ -43,10 +43,31 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         )
     }
 
+    // `return`, `break` and `continue` outside of any function body
+    pub fn misplaced_jump(&self, expr: ExprId) -> Option<Error> {
+        let span = self.ast.exprs[expr].span;
+        match self.ast.exprs[expr].kind {
+            ExprKind::Return(_) => Some(self.raw_error(
+                "`return` outside of a function",
+                [(span, "cannot `return` outside of a function")],
+            )),
+            ExprKind::Break(_) => Some(self.cannot_break(span)),
+            ExprKind::Continue(_) => Some(self.cannot_continue(span)),
+            _ => None,
+        }
+    }
+
     pub fn cannot_break(&self, span: Span) -> Error {
         self.raw_error("`break` outside of a loop", [(span, "cannot `break` outside of a loop")])
     }
 
+    pub fn undeclared_label(&self, label: Identifier) -> Error {
+        self.raw_error(
+            &format!("use of undeclared label `{}`", label.symbol),
+            [(label.span, format!("undeclared label `{}`", label.symbol))],
+        )
+    }
+
     pub fn cannot_continue(&self, span: Span) -> Error {
         self.raw_error(
             "`continue` while outside of a loop",
@@ -99,6 +120,13 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         self.raw_error(&msg, [(op.span, format!("`{}` is not valid here", op.kind.symbol()))])
     }
 
+    pub fn cannot_compare(&self, ty: Ty<'tcx>, span: Span) -> Error {
+        self.raw_error(
+            &format!("cannot compare values of type `{}`", self.tcx.display(ty)),
+            [(span, "`contains` compares elements with `==`")],
+        )
+    }
+
     pub fn cannot_index(&self, ty: Ty<'tcx>, span: Span) -> Error {
         self.raw_error(
             &format!("type `{}` cannot be indexed", self.tcx.display(ty)),
@@ -106,6 +134,10 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         )
     }
 
+    pub fn assign_overloaded_index(&self, span: Span) -> Error {
+        self.raw_error("cannot assign to an overloaded index", [(span, "`index` returns a value")])
+    }
+
     pub fn field_error(&self, ty: Ty<'tcx>, field: Identifier) -> Error {
         self.raw_error(
             &format!("no field `{}` on type `{}`", field.symbol, self.tcx.display(ty)),
@@ -149,6 +181,13 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
             [(span, format!("cannot deref `{}`", self.tcx.display(ty)))],
         )
     }
+    pub fn mutate_shared_ref(&self, ty: Ty<'tcx>, span: Span) -> Error {
+        let ty = self.tcx.try_infer_deep(ty).unwrap_or_else(|ty| ty);
+        self.raw_error(
+            &format!("cannot mutate through a shared reference `{}`", self.tcx.display(ty)),
+            [(span, "this place is behind a `&` reference")],
+        )
+    }
     pub fn ident_not_found(&self, ident: Symbol, span: Span) -> Error {
         let help = self
             .find_best_name(ident)
@@ -159,12 +198,59 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
             help.as_deref(),
         )
     }
+    pub fn cannot_capture(&self, ident: Symbol, span: Span) -> Error {
+        self.raw_error_help(
+            &format!("cannot capture local variable '{ident}'"),
+            [(span, format!("'{ident}' belongs to an enclosing function"))],
+            Some("functions and closures cannot capture their environment, pass it as a parameter"),
+        )
+    }
+    pub fn unknown_trait(&self, ident: Identifier) -> Error {
+        let Identifier { symbol, span } = ident;
+        self.raw_error(
+            &format!("cannot find trait `{symbol}` in this scope"),
+            [(span, format!("trait `{symbol}` not found"))],
+        )
+    }
+    pub fn missing_trait_method(&self, trait_: Identifier, method: Symbol) -> Error {
+        self.raw_error(
+            &format!("missing method `{method}` in implementation of `{}`", trait_.symbol),
+            [(trait_.span, format!("missing `{method}`"))],
+        )
+    }
+    pub fn trait_method_mismatch(
+        &self,
+        trait_: Identifier,
+        method: Identifier,
+        expected: Ty<'tcx>,
+        found: Ty<'tcx>,
+    ) -> Error {
+        let [expected, found] = [expected, found].map(|ty| self.tcx.display(ty));
+        self.raw_error(
+            &format!("method `{}` has an incompatible type for `{}`", method.symbol, trait_.symbol),
+            [(method.span, format!("expected `{expected}`, found `{found}`"))],
+        )
+    }
+    pub fn trait_not_implemented(&self, ty: Ty<'tcx>, trait_: Symbol, span: Span) -> Error {
+        self.raw_error(
+            &format!("the trait `{trait_}` is not implemented for `{}`", self.tcx.display(ty)),
+            [(span, format!("`{}` does not implement `{trait_}`", self.tcx.display(ty)))],
+        )
+    }
     pub fn unknown_type_err(&self, name: Symbol, span: Span) -> Error {
         self.raw_error(
             &format!("cannot find type `{name}` in this scope"),
             [(span, format!("type `{name}` not found"))],
         )
     }
+    pub fn unused_variable(&self, name: Symbol, span: Span) -> Error {
+        crate::errors::warning(
+            &format!("unused variable: `{name}`"),
+            self.path,
+            self.src,
+            [(span, format!("if this is intentional, prefix it with an underscore: `_{name}`"))],
+        )
+    }
     #[cold]
     #[inline(never)]
     pub fn subtype_err(&self, lhs: Ty<'tcx>, rhs: Ty<'tcx>, expr: ExprId) -> Error {
@@ -196,6 +282,26 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
             }),
         )
     }
+    #[cold]
+    #[inline(never)]
+    pub fn annotation_mismatch(
+        &self,
+        lhs: Ty<'tcx>,
+        rhs: Ty<'tcx>,
+        expr: ExprId,
+        annotation: Span,
+    ) -> Error {
+        let lhs = self.tcx.try_infer_deep(lhs).unwrap_or_else(|ty| ty);
+        let rhs = self.tcx.try_infer_deep(rhs).unwrap_or_else(|ty| ty);
+        let found =
+            format!("expected `{}`, found `{}`", self.tcx.display(rhs), self.tcx.display(lhs));
+        crate::errors::mismatch(
+            self.path,
+            self.src,
+            (self.ast.exprs[expr].span, found),
+            (annotation, "expected due to this"),
+        )
+    }
     fn invalid_type_span(&self, expr: ExprId) -> Vec<Span> {
         let expr = &self.ast.exprs[expr];
         match expr.kind {
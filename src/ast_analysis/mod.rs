@@ -9,12 +9,12 @@ use thin_vec::ThinVec;
 use crate::{
     HashMap,
     ast::{
-        self, Ast, BinOpKind, BinaryOp, Block, BlockId, ExprId, ExprKind, FnDecl, Identifier, Impl,
-        Lit, Pat, PatKind, Trait, TypeId, UnaryOp,
+        self, Ast, BinOpKind, BinaryOp, Binding, Block, BlockId, ExprId, ExprKind, FnDecl, Generic,
+        Identifier, Impl, Lit, Mutability, Pat, PatKind, Trait, TypeId, UnaryOp,
     },
     span::Span,
     symbol::Symbol,
-    ty::{Function, GenericRange, Interned, Ty, TyCtx, TyKind},
+    ty::{Function, GenericId, GenericRange, Interned, Ty, TyCtx, TyKey, TyKind},
 };
 
 #[derive(Default, Debug)]
@@ -23,6 +23,12 @@ pub struct TyInfo<'tcx> {
     pub type_ids: IndexVec<TypeId, Ty<'tcx>>,
     pub struct_types: HashMap<Span, Ty<'tcx>>,
     pub method_types: HashMap<ExprId, Ty<'tcx>>,
+    // the index of the field each struct field access reads
+    pub field_indices: HashMap<ExprId, usize>,
+    // the top level function each call names directly, which is how lowering recognises calls
+    // to std functions
+    pub std_calls: HashMap<ExprId, Symbol>,
+    pub warnings: Vec<Error>,
 }
 
 impl<'tcx> Index<TypeId> for TyInfo<'tcx> {
@@ -37,7 +43,8 @@ struct Body<'tcx> {
     ty_names: HashMap<Symbol, Ty<'tcx>>,
     ret: Ty<'tcx>,
     scopes: Vec<Scope<'tcx>>,
-    loops: usize,
+    /// One entry per enclosing loop with its label, set once that loop is broken out of.
+    loops: Vec<(Option<Symbol>, bool)>,
 }
 
 #[derive(Debug)]
@@ -71,6 +78,8 @@ impl<'tcx> Body<'tcx> {
 #[derive(Debug, Default)]
 struct Scope<'tcx> {
     variables: HashMap<Symbol, (Ty<'tcx>, Var)>,
+    // where each `let` binding in this scope was declared
+    decls: HashMap<Symbol, Span>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -81,7 +90,7 @@ enum Var {
 
 impl<'tcx> Body<'tcx> {
     pub fn new(ret: Ty<'tcx>) -> Self {
-        Self { ty_names: HashMap::default(), ret, scopes: vec![Scope::default()], loops: 0 }
+        Self { ty_names: HashMap::default(), ret, scopes: vec![Scope::default()], loops: vec![] }
     }
 }
 
@@ -97,6 +106,15 @@ struct Collector<'src, 'ast, 'tcx> {
     impl_generics: GenericRange,
     // the generics created by preanalyze impl/fndecl
     produced_generics: HashMap<ExprId, GenericRange>,
+    traits: HashMap<Symbol, &'ast Trait>,
+    trait_impls: Vec<(Symbol, Ty<'tcx>)>,
+    generic_bounds: HashMap<GenericId, Symbol>,
+    // types required to implement a trait, checked once inference is complete
+    obligations: Vec<(Ty<'tcx>, Symbol, Span)>,
+    // element types compared by `contains`, also checked once inference is complete
+    comparisons: Vec<(Ty<'tcx>, Span)>,
+    // `let` bindings that have not been read yet
+    unused: HashMap<Span, Symbol>,
     errors: Vec<Error>,
 }
 
@@ -106,7 +124,10 @@ fn setup_ty_info<'tcx>(ast: &Ast) -> TyInfo<'tcx> {
         expr_tys: std::iter::repeat_n(shared, ast.exprs.len()).collect(),
         type_ids: std::iter::repeat_n(shared, ast.types.len()).collect(),
         method_types: HashMap::default(),
+        field_indices: HashMap::default(),
+        std_calls: HashMap::default(),
         struct_types: HashMap::default(),
+        warnings: vec![],
     }
 }
 
@@ -129,23 +150,38 @@ pub fn analyze<'tcx>(
         fn_generics: GenericRange::EMPTY,
         impl_generics: GenericRange::EMPTY,
         produced_generics: HashMap::default(),
+        traits: HashMap::default(),
+        trait_impls: vec![],
+        generic_bounds: HashMap::default(),
+        obligations: vec![],
+        comparisons: vec![],
+        unused: HashMap::default(),
         errors: vec![],
     };
     let top_level_exprs = ast.top_level.iter().copied().collect();
     let top_level = ast::Block { span: Span::ZERO, stmts: top_level_exprs, is_expr: false };
     collector.analyze_body_with(&top_level, Body::new(Ty::NEVER)).map_err(|err| vec![err])?;
+    collector.check_obligations();
 
     if !collector.errors.is_empty() {
         return Err(collector.errors);
     }
 
     let mut ty_info = std::mem::take(&mut collector.ty_info);
+    let mut unused: Vec<_> = collector.unused.iter().map(|(&span, &name)| (span, name)).collect();
+    unused.sort_by_key(|(span, _)| span.start());
+    ty_info.warnings =
+        unused.into_iter().map(|(span, name)| collector.unused_variable(name, span)).collect();
     for (expr, ty) in std::iter::zip(&ast.exprs, &mut ty_info.expr_tys) {
         *ty = tcx.try_infer_deep(*ty).map_err(|ty| vec![collector.cannot_infer(ty, expr.span)])?;
     }
-    ty_info.type_ids.iter_mut().for_each(|ty| *ty = tcx.infer_deep(*ty));
+    for (ast_ty, ty) in std::iter::zip(&ast.types, &mut ty_info.type_ids) {
+        *ty =
+            tcx.try_infer_deep(*ty).map_err(|ty| vec![collector.cannot_infer(ty, ast_ty.span)])?;
+    }
     ty_info.method_types.values_mut().for_each(|ty| *ty = tcx.infer_deep(*ty));
     ty_info.struct_types.values_mut().for_each(|ty| *ty = tcx.infer_deep(*ty));
+    debug_assert!(ty_info.expr_tys.iter().chain(&ty_info.type_ids).all(|ty| !ty.has_infer()));
 
     Ok(ty_info)
 }
@@ -164,25 +200,37 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         block: &ast::Block,
         mut body: Body<'tcx>,
     ) -> Result<(Ty<'tcx>, Body<'tcx>)> {
-        // look for structs/enums first.
+        // traits can be used as bounds by any other item.
         for id in &block.stmts {
-            let ExprKind::Struct { ident, generics, fields } = &self.ast.exprs[*id].kind else {
+            if let ExprKind::Trait(trait_) = &self.ast.exprs[*id].kind {
+                self.traits.insert(trait_.ident.symbol, trait_);
+            }
+        }
+        // look for structs/enums first.
+        // every name is declared before any fields are read, so structs can refer to each other
+        // and to themselves in any order
+        let mut structs = vec![];
+        for &id in &block.stmts {
+            let ExprKind::Struct { ident, generics, fields } = &self.ast.exprs[id].kind else {
                 continue;
             };
             let symbols: ThinVec<_> = fields.iter().map(|field| field.ident.symbol).collect();
-            let generics = self.tcx.new_generics(generics);
+            let generics = self.new_generics(generics);
+            let struct_ty = self.tcx.new_struct(ident.symbol, generics, symbols);
+            self.current().ty_names.insert(ident.symbol, struct_ty);
+            self.ty_info.struct_types.insert(ident.span, struct_ty);
+            structs.push((ident, generics, fields, struct_ty));
+        }
+        for (ident, generics, fields, struct_ty) in structs {
             self.impl_generics = generics;
-
             let fields: ThinVec<_> =
                 fields.iter().map(|field| self.read_ast_ty(field.ty)).collect();
-            let params = fields.clone();
-            let struct_ty = self.tcx.new_struct(ident.symbol, generics, symbols, fields);
-            self.current().ty_names.insert(ident.symbol, struct_ty);
-            self.ty_info.struct_types.insert(ident.span, struct_ty);
+            let TyKind::Struct { id, .. } = *struct_ty.0 else { unreachable!() };
+            self.tcx.set_struct_fields(id, &fields);
 
             body.insert_var(
                 *ident,
-                self.tcx.intern(TyKind::Function(Function { params, ret: struct_ty })),
+                self.tcx.intern(TyKind::Function(Function { params: fields, ret: struct_ty })),
                 Var::Const,
             );
         }
@@ -191,7 +239,7 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
             match &self.ast.exprs[id].kind {
                 ExprKind::FnDecl(func) => self.preanalyze_fndecl(&mut body, func, id)?,
                 ExprKind::Impl(impl_) => {
-                    let generics = self.tcx.new_generics(&impl_.generics);
+                    let generics = self.new_generics(&impl_.generics);
                     self.impl_generics = generics;
                     self.produced_generics.insert(id, self.impl_generics);
                     let ty = self.read_ast_ty_with(impl_.ty, None);
@@ -201,6 +249,21 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                         };
                         self.preanalyze_method(&body, ty, func, method_id);
                     }
+                    if let Some(trait_) = impl_.trait_ {
+                        self.preanalyze_trait_impl(trait_, impl_, ty);
+                    }
+                    for &const_id in &impl_.consts {
+                        let ExprKind::Const { ident, ty: const_ty, .. } =
+                            self.ast.exprs[const_id].kind
+                        else {
+                            unreachable!()
+                        };
+                        let const_ty = match const_ty {
+                            Some(const_ty) => self.read_ast_ty_with(const_ty, Some(ty)),
+                            None => self.tcx.new_infer(),
+                        };
+                        self.tcx.add_const(ty, ident.symbol, const_ty);
+                    }
                 }
                 _ => {}
             }
@@ -217,8 +280,10 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         id: ExprId,
     ) -> Result<()> {
         let FnDecl { ident, generics, params, ret, .. } = fndecl;
-        self.fn_generics = self.tcx.new_generics(generics);
+        self.fn_generics = self.new_generics(generics);
         self.produced_generics.insert(id, self.fn_generics);
+        // fn items can't use the generics of an enclosing impl
+        let impl_generics = std::mem::replace(&mut self.impl_generics, GenericRange::EMPTY);
         let ret = match ret {
             Some(ret) => self.read_ast_ty(*ret),
             None => Ty::UNIT,
@@ -234,6 +299,7 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                 }
             })
             .collect();
+        self.impl_generics = impl_generics;
         let prev = body.insert_var(
             *ident,
             self.tcx.intern(TyKind::Function(Function { params, ret })),
@@ -245,9 +311,14 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
 
     fn preanalyze_method(&mut self, body: &Body<'tcx>, ty: Ty<'tcx>, fndecl: &FnDecl, id: ExprId) {
         _ = body;
-        let FnDecl { ident, generics, params, ret, .. } = fndecl;
-        self.fn_generics = self.tcx.new_generics(generics);
+        self.fn_generics = self.new_generics(&fndecl.generics);
         self.produced_generics.insert(id, self.fn_generics);
+        let fn_ty = self.method_signature(ty, fndecl);
+        self.tcx.add_method(ty, fndecl.ident.symbol, fn_ty);
+    }
+
+    fn method_signature(&mut self, ty: Ty<'tcx>, fndecl: &FnDecl) -> Function<'tcx> {
+        let FnDecl { params, ret, .. } = fndecl;
         let ret = match ret {
             Some(ret) => self.read_ast_ty_with(*ret, Some(ty)),
             None => Ty::UNIT,
@@ -264,9 +335,101 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                 }
             })
             .collect();
+        Function { params, ret }
+    }
+
+    // each method of the trait must be implemented with the signature the trait declares
+    fn preanalyze_trait_impl(&mut self, trait_: Identifier, impl_: &Impl, ty: Ty<'tcx>) {
+        let Some(&decl) = self.traits.get(&trait_.symbol) else {
+            self.errors.push(self.unknown_trait(trait_));
+            return;
+        };
+        for method in &decl.methods {
+            let symbol = method.ident.symbol;
+            let implemented = impl_.methods.iter().find_map(|&id| match &self.ast.exprs[id].kind {
+                ExprKind::FnDecl(func) if func.ident.symbol == symbol => Some(func),
+                _ => None,
+            });
+            let Some(implemented) = implemented else {
+                self.errors.push(self.missing_trait_method(trait_, symbol));
+                continue;
+            };
+            // the generics of a trait method are chosen by each impl, such as the key of `Index`
+            self.fn_generics = self.new_generics(&method.generics);
+            let expected = self.method_signature(ty, method);
+            let found = self.tcx.get_method(ty, symbol).unwrap();
+            let chosen = self.tcx.intern(TyKind::Function(expected.caller(self.tcx)));
+            if self.tcx.eq(chosen, self.tcx.intern(TyKind::Function(found.clone()))).is_err() {
+                let [expected, found] =
+                    [expected, found.clone()].map(|func| self.tcx.intern(TyKind::Function(func)));
+                let err = self.trait_method_mismatch(trait_, implemented.ident, expected, found);
+                self.errors.push(err);
+            }
+        }
+        self.trait_impls.push((trait_.symbol, ty));
+    }
+
+    // the signature of a method provided by the trait bound of a generic
+    fn bound_method(
+        &mut self,
+        ty: Ty<'tcx>,
+        generic: GenericId,
+        method: Symbol,
+    ) -> Option<Function<'tcx>> {
+        let trait_ = self.traits.get(self.generic_bounds.get(&generic)?)?;
+        let decl = trait_.methods.iter().find(|decl| decl.ident.symbol == method)?;
+        Some(self.method_signature(ty, decl))
+    }
+
+    fn new_generics(&mut self, generics: &[Generic]) -> GenericRange {
+        let range = self.tcx.new_generics(generics);
+        for (id, generic) in range.iter().zip(generics) {
+            let Some(bound) = generic.bound else { continue };
+            if !self.traits.contains_key(&bound.symbol) {
+                self.errors.push(self.unknown_trait(bound));
+            }
+            self.generic_bounds.insert(id, bound.symbol);
+        }
+        range
+    }
+
+    // instantiates a function's generics, bounded generics must implement their trait
+    fn instantiate(&mut self, func: &Function<'tcx>, span: Span) -> Function<'tcx> {
+        let mut map = HashMap::default();
+        let func = func.caller_with(self.tcx, &mut map);
+        for (generic, ty) in map {
+            if let Some(&trait_) = self.generic_bounds.get(&generic) {
+                self.obligations.push((ty, trait_, span));
+            }
+        }
+        func
+    }
+
+    fn check_obligations(&mut self) {
+        for (ty, trait_, span) in std::mem::take(&mut self.obligations) {
+            let Ok(ty) = self.tcx.try_infer_deep(ty) else { continue };
+            if !self.implements(ty, trait_) {
+                self.errors.push(self.trait_not_implemented(ty, trait_, span));
+            }
+        }
+        for (ty, span) in std::mem::take(&mut self.comparisons) {
+            let Ok(ty) = self.tcx.try_infer_deep(ty) else { continue };
+            if !self.is_comparable(ty) {
+                self.errors.push(self.cannot_compare(ty, span));
+            }
+        }
+    }
 
-        let fn_ty = Function { params, ret };
-        self.tcx.add_method(ty, ident.symbol, fn_ty);
+    fn implements(&self, ty: Ty<'tcx>, trait_: Symbol) -> bool {
+        match ty.0 {
+            TyKind::Poison | TyKind::Never => true,
+            TyKind::Generic(generic) => self.generic_bounds.get(generic) == Some(&trait_),
+            // `Ord` is derived for structs and tuples of ordered fields
+            TyKind::Struct { .. } | TyKind::Tuple(_) if trait_ == "Ord" => self.is_ordered(ty),
+            _ => self.trait_impls.iter().any(|&(implemented, impl_ty)| {
+                implemented == trait_ && TyKey(impl_ty) == TyKey(ty)
+            }),
+        }
     }
     fn current(&mut self) -> &mut Body<'tcx> {
         self.bodies.last_mut().unwrap()
@@ -293,6 +456,16 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         })
     }
 
+    // the enclosing loop a `break` or `continue` jumps to, the innermost one without a label
+    fn jump_target(&mut self, label: Option<ast::Identifier>) -> Result<usize> {
+        let loops = &self.current().loops;
+        let Some(label) = label else { return Ok(loops.len() - 1) };
+        match loops.iter().rposition(|&(name, _)| name == Some(label.symbol)) {
+            Some(target) => Ok(target),
+            None => Err(self.undeclared_label(label)),
+        }
+    }
+
     fn read_ast_ty(&mut self, id: ast::TypeId) -> Ty<'tcx> {
         self.read_ast_ty_with(id, None)
     }
@@ -300,7 +473,9 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
     fn read_ast_ty_with(&mut self, id: ast::TypeId, for_ty: Option<Ty<'tcx>>) -> Ty<'tcx> {
         let ast_ty = &self.ast.types[id];
         let ty = match ast_ty.kind {
-            ast::TyKind::Ref(of) => self.tcx.intern(TyKind::Ref(self.read_ast_ty_with(of, for_ty))),
+            ast::TyKind::Ref(of, mutability) => {
+                self.tcx.intern(TyKind::Ref(self.read_ast_ty_with(of, for_ty), mutability))
+            }
             ast::TyKind::Func { ref params, ret } => {
                 let ret = match ret {
                     Some(ty) => self.read_ast_ty_with(ty, for_ty),
@@ -315,6 +490,10 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
             ast::TyKind::Array(of) => {
                 self.tcx.intern(TyKind::Array(self.read_ast_ty_with(of, for_ty)))
             }
+            ast::TyKind::Tuple(ref elems) => {
+                let elems = elems.iter().map(|elem| self.read_ast_ty_with(*elem, for_ty)).collect();
+                self.tcx.intern(TyKind::Tuple(elems))
+            }
             ast::TyKind::Name { ident, .. } if ident == "_" => self.tcx.new_infer(),
             ast::TyKind::Name { ident, .. } if ident == "self" => {
                 if let Some(ty) = for_ty {
@@ -324,6 +503,14 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                     Ty::POISON
                 }
             }
+            ast::TyKind::Name { ident, ref generics } if ident == "Map" && generics.len() == 2 => {
+                let key = self.read_ast_ty_with(generics[0], for_ty);
+                let value = self.read_ast_ty_with(generics[1], for_ty);
+                self.tcx.intern(TyKind::Map(key, value))
+            }
+            ast::TyKind::Name { ident, ref generics } if ident == "Set" && generics.len() == 1 => {
+                self.tcx.intern(TyKind::Set(self.read_ast_ty_with(generics[0], for_ty)))
+            }
             ast::TyKind::Name { ident, ref generics } => {
                 if generics.is_empty() {
                     match ([self.impl_generics, self.fn_generics].iter().copied().flatten())
@@ -335,15 +522,12 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                 } else {
                     let ty = self.read_named_ty(ident, ast_ty.span);
                     match ty.0 {
-                        TyKind::Struct { generics: generic_range, .. } => {
-                            // TODO: hashmap is not needed
-                            let mut map = HashMap::default();
+                        TyKind::Struct { id, generics: declared } => {
                             // TODO: custom error here
-                            assert!(generics.len() == generic_range.len as usize);
-                            for (id, ty) in generic_range.iter().zip(generics) {
-                                map.insert(id, self.read_ast_ty(*ty));
-                            }
-                            ty.replace_generics(self.tcx, |id| map[&id])
+                            assert!(generics.len() == declared.len());
+                            let generics =
+                                generics.iter().map(|&ty| self.read_ast_ty(ty)).collect();
+                            self.tcx.intern(TyKind::Struct { id: *id, generics })
                         }
                         _ => unreachable!(),
                     }
@@ -370,12 +554,24 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         Infer { out: Ok(()) }
     }
 
-    fn eq_block(&mut self, lhs: Ty<'tcx>, rhs: Ty<'tcx>, block: BlockId) -> Infer {
-        if let Err([lhs, rhs]) = self.tcx.eq(lhs, rhs) {
-            self.errors.push(self.subtype_err_block(lhs, rhs, block));
-            return Infer { out: Err(()) };
+    /// The type of two branches, where a branch that never produces a value
+    /// takes on the type of the other.
+    fn join(&mut self, lhs: Ty<'tcx>, rhs: Ty<'tcx>) -> Result<Ty<'tcx>, [Ty<'tcx>; 2]> {
+        let is_never = |ty| self.tcx.try_infer_shallow(ty).is_ok_and(|ty| ty.is_never());
+        if is_never(lhs) {
+            return Ok(rhs);
         }
-        Infer { out: Ok(()) }
+        if is_never(rhs) {
+            return Ok(lhs);
+        }
+        self.tcx.eq(lhs, rhs).map(|()| lhs)
+    }
+
+    fn join_block(&mut self, expected: Ty<'tcx>, ty: Ty<'tcx>, block: BlockId) -> Ty<'tcx> {
+        self.join(expected, ty).unwrap_or_else(|[expected, ty]| {
+            self.errors.push(self.subtype_err_block(ty, expected, block));
+            expected
+        })
     }
 
     fn sub(&mut self, lhs: Ty<'tcx>, rhs: Ty<'tcx>, expr: ExprId) -> Infer {
@@ -386,6 +582,15 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         Infer { out: Ok(()) }
     }
 
+    fn sub_annotated(&mut self, lhs: Ty<'tcx>, rhs: Ty<'tcx>, expr: ExprId, ty: TypeId) -> Infer {
+        if let Err([lhs, rhs]) = self.tcx.sub(lhs, rhs) {
+            let annotation = self.ast.types[ty].span;
+            self.errors.push(self.annotation_mismatch(lhs, rhs, expr, annotation));
+            return Infer { out: Err(()) };
+        }
+        Infer { out: Ok(()) }
+    }
+
     fn sub_span(&mut self, lhs: Ty<'tcx>, rhs: Ty<'tcx>, span: Span) -> Infer {
         if let Err([lhs, rhs]) = self.tcx.sub(lhs, rhs) {
             self.errors.push(self.subtype_err_inner(lhs, rhs, vec![span]));
@@ -406,14 +611,13 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
     fn analyze_expr(&mut self, id: ExprId) -> Result<Ty<'tcx>> {
         let expr_span = self.ast.exprs[id].span;
         if !self.within_const && self.bodies.len() <= 2 && !self.is_item(id) {
-            return Err(self.expected_item(id));
+            return Err(self.misplaced_jump(id).unwrap_or_else(|| self.expected_item(id)));
         }
         if self.within_const && !self.is_const(id) {
-            return Err(self.expected_const(id));
+            return Err(self.misplaced_jump(id).unwrap_or_else(|| self.expected_const(id)));
         }
 
         let ty = match self.ast.exprs[id].kind {
-            ExprKind::Trait(ref trait_) => self.analyze_trait(trait_, id)?,
             ExprKind::Impl(ref impl_) => self.analyze_impl(impl_, id)?,
             ExprKind::Assert(expr) => {
                 let ty = self.analyze_expr(expr)?;
@@ -427,10 +631,16 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                 let ty = match op {
                     UnaryOp::Neg => Ty::INT,
                     UnaryOp::Not => Ty::BOOL,
-                    UnaryOp::Ref => break 'outer self.tcx.intern(TyKind::Ref(operand)),
+                    UnaryOp::Ref => {
+                        break 'outer self.tcx.intern(TyKind::Ref(operand, Mutability::Not));
+                    }
+                    UnaryOp::RefMut => {
+                        self.check_mutable_place(expr)?;
+                        break 'outer self.tcx.intern(TyKind::Ref(operand, Mutability::Mut));
+                    }
                     UnaryOp::Deref => {
                         let operand = self.tcx.infer_shallow(operand);
-                        let TyKind::Ref(inner) = operand.0 else {
+                        let TyKind::Ref(inner, _) = operand.0 else {
                             return Err(self.cannot_deref(operand, expr_span));
                         };
                         break 'outer *inner;
@@ -438,15 +648,39 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                 };
                 self.sub(operand, ty, id).then(|| ty)
             }
-            ExprKind::Binary { lhs, op, rhs } => self.analyze_binary_expr(lhs, op, rhs)?,
-            ExprKind::Index { expr, index } => self.index(expr, index, expr_span)?,
-            ExprKind::FnCall { function, ref args } => {
+            ExprKind::Binary { lhs, op, rhs } => self.analyze_binary_expr(lhs, op, rhs, id)?,
+            ExprKind::Index { expr, index } => self.index(expr, index, id)?,
+            ExprKind::FnCall { function, ref args } => 'call: {
+                let std_fn = self.std_fn(function);
+                if let Some(name) = std_fn {
+                    self.ty_info.std_calls.insert(id, name);
+                }
+                let std_fn = std_fn.map(Symbol::as_str);
+
+                // `print` and `println` also take several arguments, of any type. Each of them
+                // is formatted by its own type, so the generic function itself is never used
+                if args.len() > 1 && matches!(std_fn, Some("print" | "println")) {
+                    for &arg_id in args {
+                        self.analyze_expr(arg_id)?;
+                    }
+                    break 'call Ty::UNIT;
+                }
+
                 let fn_ty = self.analyze_expr(function)?;
                 let TyKind::Function(Function { params, ret }) = fn_ty.0 else {
                     let fn_span = self.ast.exprs[function].span;
                     return Err(self.expected_function(fn_ty, fn_span));
                 };
 
+                // as does `concat`, though every argument must be a `str`
+                if args.len() > 1 && std_fn == Some("concat") {
+                    for &arg_id in args {
+                        let arg = self.analyze_expr(arg_id)?;
+                        self.sub(arg, Ty::STR, arg_id);
+                    }
+                    break 'call *ret;
+                }
+
                 if args.len() != params.len() {
                     return Err(self.invalid_arg_count(
                         args.len(),
@@ -460,14 +694,26 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                     let arg = self.analyze_expr(arg_id)?;
                     self.sub(arg, *param, arg_id);
                 }
+                if std_fn == Some("contains") {
+                    self.comparisons.push((params[1], expr_span));
+                }
                 *ret
             }
             ExprKind::MethodCall { expr, method, ref args } => {
                 let ty = self.tcx.infer_shallow(self.analyze_expr(expr)?);
-                let Some(func) = self.tcx.get_method(ty, method.symbol) else {
+                // generics are not instantiated within their own body
+                let func = match ty.fully_deref().0 {
+                    &TyKind::Generic(generic) => {
+                        self.bound_method(ty.fully_deref(), generic, method.symbol)
+                    }
+                    _ => self
+                        .tcx
+                        .get_method(ty, method.symbol)
+                        .map(|func| self.instantiate(func, method.span)),
+                };
+                let Some(func) = func else {
                     return Err(self.method_not_found(ty, method));
                 };
-                let func = func.caller(self.tcx);
 
                 let Function { ref params, ret } = func;
 
@@ -481,6 +727,15 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                 }
 
                 self.anyref_sub(ty, params[0], expr);
+                if let TyKind::Ref(_, Mutability::Mut) = self.tcx.infer_shallow(params[0]).0 {
+                    match ty.0 {
+                        TyKind::Ref(_, Mutability::Not) => {
+                            return Err(self.mutate_shared_ref(ty, self.ast.exprs[expr].span));
+                        }
+                        TyKind::Ref(_, Mutability::Mut) => {}
+                        _ => self.check_mutable_place(expr)?,
+                    }
+                }
 
                 for (&arg_id, param) in args.iter().zip(&params[1..]) {
                     let arg = self.analyze_expr(arg_id)?;
@@ -493,25 +748,31 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                 ret
             }
             ExprKind::FnDecl(ref decl) => self.analyze_fndecl(decl, id)?,
-            ExprKind::Struct { .. } => Ty::UNIT,
-            ExprKind::Let { ident, ty, expr } => {
+            ExprKind::Closure { ref params, body } => self.analyze_closure(params, body)?,
+            // trait methods are only declared, their signatures are read when used by a bound
+            ExprKind::Struct { .. } | ExprKind::Trait(..) => Ty::UNIT,
+            ExprKind::Let { ref binding, ty, expr } => {
                 let expr_ty = self.analyze_expr(expr)?;
-                let ty = if let Some(ty) = ty {
-                    let ty = self.read_ast_ty(ty);
-                    self.sub(expr_ty, ty, expr).then(|| ty)
+                let ty = if let Some(ast_ty) = ty {
+                    let ty = self.read_ast_ty(ast_ty);
+                    self.sub_annotated(expr_ty, ty, expr, ast_ty).then(|| ty)
                 } else {
                     expr_ty
                 };
-                self.insert_var(ident, ty, Var::Let);
+                if let Binding::Tuple(..) = binding {
+                    let binding_ty = self.binding_ty(binding);
+                    self.sub(ty, binding_ty, expr);
+                }
+                self.insert_binding(binding, ty);
                 Ty::UNIT
             }
             ExprKind::Const { ident, ty, expr } => {
                 let within_const = std::mem::replace(&mut self.within_const, true);
                 let expr_ty = self.analyze_expr(expr)?;
                 self.within_const = within_const;
-                let ty = if let Some(ty) = ty {
-                    let ty = self.read_ast_ty(ty);
-                    self.sub(expr_ty, ty, expr).then(|| ty)
+                let ty = if let Some(ast_ty) = ty {
+                    let ty = self.read_ast_ty(ast_ty);
+                    self.sub_annotated(expr_ty, ty, expr, ast_ty).then(|| ty)
                 } else {
                     expr_ty
                 };
@@ -521,12 +782,13 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                 self.insert_var(ident, ty, Var::Const);
                 Ty::UNIT
             }
-            ExprKind::For { ident, iter, body } => {
+            ExprKind::For { ident, iter, body, label } => {
                 // for now only allow ranges
                 let iter_ty = self.analyze_expr(iter)?;
                 let iter_ty = self.tcx.infer_shallow(iter_ty);
                 let ident_ty = match iter_ty.0 {
                     TyKind::Range => Ty::INT,
+                    TyKind::CharRange => Ty::CHAR,
                     TyKind::Array(of) => *of,
                     _ => return Err(self.cannot_iter(iter_ty, self.ast.exprs[iter].span)),
                 };
@@ -534,24 +796,33 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                 self.current().scopes.push(Scope::default());
                 self.insert_var(ident, ident_ty, Var::Let);
 
-                self.current().loops += 1;
+                self.current().loops.push((label.map(|label| label.symbol), false));
                 let out = self.analyze_block(body)?;
-                self.current().loops -= 1;
+                self.current().loops.pop();
                 self.current().scopes.pop().unwrap();
 
                 self.sub_block(out, Ty::UNIT, body);
                 Ty::UNIT
             }
-            ExprKind::While { condition, block } => {
+            ExprKind::While { condition, block, label } => {
                 let condition_ty = self.analyze_expr(condition)?;
                 self.current().scopes.push(Scope::default());
                 self.sub(condition_ty, Ty::BOOL, condition);
-                self.current().loops += 1;
+                self.current().loops.push((label.map(|label| label.symbol), false));
                 self.analyze_block(block)?;
-                self.current().loops -= 1;
+                self.current().loops.pop();
                 self.current().scopes.pop().unwrap();
                 Ty::UNIT
             }
+            ExprKind::Loop { block, label } => {
+                self.current().scopes.push(Scope::default());
+                self.current().loops.push((label.map(|label| label.symbol), false));
+                self.analyze_block(block)?;
+                let (_, broken) = self.current().loops.pop().unwrap();
+                self.current().scopes.pop().unwrap();
+                // without a `break` the loop can only be left by returning
+                if broken { Ty::UNIT } else { Ty::NEVER }
+            }
             ExprKind::Match { scrutinee, ref arms } => {
                 let mut ty = None;
                 let scrutinee = self.analyze_expr(scrutinee)?;
@@ -559,12 +830,13 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                     self.current().scopes.push(Scope::default());
                     self.analyze_pat(&arm.pat, scrutinee)?;
                     let arm_ty = self.analyze_expr(arm.body)?;
-                    match ty {
-                        None => ty = Some(arm_ty),
-                        Some(ty) => {
-                            self.eq(arm_ty, ty, arm.body);
-                        }
-                    }
+                    ty = Some(match ty {
+                        None => arm_ty,
+                        Some(ty) => self.join(ty, arm_ty).unwrap_or_else(|[ty, arm_ty]| {
+                            self.errors.push(self.subtype_err(arm_ty, ty, arm.body));
+                            ty
+                        }),
+                    });
                     self.current().scopes.pop().unwrap();
                 }
                 // TODO: produce error here instead
@@ -577,21 +849,22 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                     let ty = self.analyze_expr(arm.condition)?;
                     self.sub(ty, Ty::BOOL, id);
                     let block_ty = self.analyze_block(arm.body)?;
-                    if let Some(expected_ty) = expected_ty {
-                        self.eq_block(expected_ty, block_ty, arm.body);
-                    } else {
-                        expected_ty = Some(block_ty);
-                    }
+                    expected_ty = Some(match expected_ty {
+                        None => block_ty,
+                        Some(expected_ty) => self.join_block(expected_ty, block_ty, arm.body),
+                    });
                 }
                 let expected_ty = expected_ty.unwrap();
                 if let Some(els) = els {
                     let block_ty = self.analyze_block(els)?;
-                    self.sub_block(expected_ty, block_ty, els);
+                    self.join_block(expected_ty, block_ty, els)
                 } else {
                     // TODO: specialized error message here.
-                    self.sub(expected_ty, Ty::UNIT, id);
+                    self.join(expected_ty, Ty::UNIT).unwrap_or_else(|[expected_ty, unit]| {
+                        self.errors.push(self.subtype_err(expected_ty, unit, id));
+                        expected_ty
+                    })
                 }
-                expected_ty
             }
             ExprKind::Block(block_id) => {
                 let block = &self.ast.blocks[block_id];
@@ -612,29 +885,51 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
                 self.sub(ty, expected, expr.unwrap_or(id));
                 Ty::NEVER
             }
-            ExprKind::Break => {
-                if self.current().loops == 0 {
+            ExprKind::Break(label) => {
+                if self.current().loops.is_empty() {
                     return Err(self.cannot_break(self.ast.exprs[id].span));
                 }
+                let target = self.jump_target(label)?;
+                self.current().loops[target].1 = true;
                 Ty::NEVER
             }
-            ExprKind::Continue => {
-                if self.current().loops == 0 {
+            ExprKind::Continue(label) => {
+                if self.current().loops.is_empty() {
                     return Err(self.cannot_continue(self.ast.exprs[id].span));
                 }
+                self.jump_target(label)?;
                 Ty::NEVER
             }
             ExprKind::Unreachable => Ty::NEVER,
-            ExprKind::FieldAccess { expr, field } => {
+            ExprKind::FieldAccess { expr, field } => 'field: {
+                // associated constants are lowered to calls of a function without parameters
+                if let Some((owner, const_ty)) = self.assoc_const(expr, field) {
+                    let fn_ty = Function { params: ThinVec::new(), ret: const_ty };
+                    self.ty_info.method_types.insert(id, self.tcx.intern(TyKind::Function(fn_ty)));
+                    self.ty_info.expr_tys[expr] = owner;
+                    break 'field const_ty;
+                }
                 let expr = self.tcx.infer_shallow(self.analyze_expr(expr)?);
-                let TyKind::Struct { symbols, fields, .. } = expr.0 else {
-                    return Err(self.field_error(expr, field));
+                // fields are accessed through any number of references
+                let mut strukt = expr;
+                while let TyKind::Ref(of, _) = strukt.0 {
+                    strukt = self.tcx.infer_shallow(*of);
+                }
+                let field_ty = match strukt.0 {
+                    TyKind::Struct { id: struct_id, .. } => {
+                        let symbols = self.tcx.struct_symbols(*struct_id);
+                        let i = symbols.iter().position(|&s| s == field.symbol);
+                        i.map(|i| {
+                            self.ty_info.field_indices.insert(id, i);
+                            self.tcx.struct_fields(strukt)[i]
+                        })
+                    }
+                    TyKind::Tuple(elems) => {
+                        field.symbol.parse::<usize>().ok().and_then(|i| elems.get(i).copied())
+                    }
+                    _ => None,
                 };
-                let field = symbols
-                    .iter()
-                    .position(|&s| s == field.symbol)
-                    .ok_or_else(|| self.field_error(expr, field))?;
-                fields[field]
+                field_ty.ok_or_else(|| self.field_error(expr, field))?
             }
         };
         self.ty_info.expr_tys[id] = ty;
@@ -645,7 +940,7 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         loop {
             lhs = self.tcx.infer_shallow(lhs);
             match lhs.0 {
-                TyKind::Ref(of) => lhs = *of,
+                TyKind::Ref(of, _) => lhs = *of,
                 _ => break,
             }
         }
@@ -653,7 +948,7 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         loop {
             rhs = self.tcx.infer_shallow(rhs);
             match rhs.0 {
-                TyKind::Ref(of) => rhs = *of,
+                TyKind::Ref(of, _) => rhs = *of,
                 _ => break,
             }
         }
@@ -662,6 +957,39 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
 
     fn insert_var(&mut self, ident: Identifier, ty: Ty<'tcx>, kind: Var) {
         self.current().insert_var(ident, ty, kind);
+        let decls = &mut self.current().scope().decls;
+        if matches!(kind, Var::Let) && !ident.symbol.starts_with('_') {
+            decls.insert(ident.symbol, ident.span);
+            self.unused.insert(ident.span, ident.symbol);
+        } else {
+            decls.remove(&ident.symbol);
+        }
+    }
+
+    // the shape a binding destructures, with a fresh inference variable for each name
+    fn binding_ty(&self, binding: &Binding) -> Ty<'tcx> {
+        match binding {
+            Binding::Ident(_) => self.tcx.new_infer(),
+            Binding::Tuple(elems) => {
+                let elems = elems.iter().map(|elem| self.binding_ty(elem)).collect();
+                self.tcx.intern(TyKind::Tuple(elems))
+            }
+        }
+    }
+
+    fn insert_binding(&mut self, binding: &Binding, ty: Ty<'tcx>) {
+        match binding {
+            Binding::Ident(ident) => self.insert_var(*ident, ty, Var::Let),
+            Binding::Tuple(elems) => {
+                let tys = match self.tcx.try_infer_shallow(ty).map(|ty| ty.0) {
+                    Ok(TyKind::Tuple(tys)) if tys.len() == elems.len() => tys.clone(),
+                    _ => elems.iter().map(|_| Ty::POISON).collect(),
+                };
+                for (elem, ty) in elems.iter().zip(tys) {
+                    self.insert_binding(elem, ty);
+                }
+            }
+        }
     }
 
     fn analyze_pat(&mut self, pat: &Pat, scrutinee: Ty<'tcx>) -> Result<()> {
@@ -686,11 +1014,20 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         Ok(())
     }
 
-    fn analyze_binary_expr(&mut self, lhs: ExprId, op: BinaryOp, rhs: ExprId) -> Result<Ty<'tcx>> {
+    fn analyze_binary_expr(
+        &mut self,
+        lhs: ExprId,
+        op: BinaryOp,
+        rhs: ExprId,
+        id: ExprId,
+    ) -> Result<Ty<'tcx>> {
         use BinOpKind as B;
 
         let mut lhs_ty = self.analyze_expr(lhs)?;
         let mut rhs_ty = self.analyze_expr(rhs)?;
+        if op.kind == BinOpKind::Assign || op.is_op_assign() {
+            self.check_mutable_place(lhs)?;
+        }
 
         match op.kind {
             BinOpKind::Assign => {}
@@ -701,6 +1038,9 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
             }
         }
 
+        if let Some(ty) = self.overloaded_binop(lhs_ty, op, rhs_ty, rhs, id) {
+            return Ok(ty);
+        }
         self.enforce_valid_binop(lhs_ty, op, rhs_ty, lhs, rhs)?;
         let infer = self.sub(rhs_ty, lhs_ty, rhs);
 
@@ -714,11 +1054,79 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
             B::And | B::Or | B::Less | B::Greater | B::LessEq | B::GreaterEq | B::Eq | B::Neq => {
                 Ty::BOOL
             }
+            B::RangeInclusive | B::Range if self.tcx.infer_shallow(lhs_ty).is_char() => {
+                Ty::CHAR_RANGE
+            }
             B::RangeInclusive | B::Range => Ty::RANGE,
             B::Add | B::Sub | B::Mul | B::Div | B::Mod => infer.then(|| lhs_ty),
         })
     }
 
+    // `+`, `-` and `==` on a struct call the method of its `Add`, `Sub` or `Eq` impl
+    fn overloaded_binop(
+        &mut self,
+        lhs_ty: Ty<'tcx>,
+        op: BinaryOp,
+        rhs_ty: Ty<'tcx>,
+        rhs: ExprId,
+        id: ExprId,
+    ) -> Option<Ty<'tcx>> {
+        let (trait_, method) = match op.kind {
+            BinOpKind::Add => ("Add", "add"),
+            BinOpKind::Sub => ("Sub", "sub"),
+            BinOpKind::Eq | BinOpKind::Neq => ("Eq", "eq"),
+            _ => return None,
+        };
+        let ret = self.call_operator(trait_, method, lhs_ty, rhs_ty, rhs, id)?;
+        Some(if op.kind == BinOpKind::Neq { Ty::BOOL } else { ret })
+    }
+
+    // the return type of `method` when the struct `lhs_ty` implements `trait_`
+    fn call_operator(
+        &mut self,
+        trait_: &str,
+        method: &str,
+        lhs_ty: Ty<'tcx>,
+        rhs_ty: Ty<'tcx>,
+        rhs: ExprId,
+        id: ExprId,
+    ) -> Option<Ty<'tcx>> {
+        let lhs_ty = self.tcx.try_infer_shallow(lhs_ty).ok()?.fully_deref();
+        if !matches!(lhs_ty.0, TyKind::Struct { .. }) || !self.implements(lhs_ty, trait_.into()) {
+            return None;
+        }
+        let func = self.tcx.get_method(lhs_ty, method.into())?;
+        let func = self.instantiate(func, self.ast.exprs[id].span);
+        self.sub(rhs_ty, func.params[1], rhs);
+        let ret = func.ret;
+        self.ty_info.method_types.insert(id, self.tcx.intern(TyKind::Function(func)));
+        Some(ret)
+    }
+
+    // errors if writing to `place` would go through a shared reference
+    fn check_mutable_place(&self, place: ExprId) -> Result<()> {
+        let is_index = matches!(self.ast.exprs[place].kind, ExprKind::Index { .. });
+        if is_index && self.ty_info.method_types.contains_key(&place) {
+            return Err(self.assign_overloaded_index(self.ast.exprs[place].span));
+        }
+        let (ExprKind::Unary { op: UnaryOp::Deref, expr }
+        | ExprKind::FieldAccess { expr, .. }
+        | ExprKind::Index { expr, .. }) = self.ast.exprs[place].kind
+        else {
+            return Ok(());
+        };
+        let ty = self.tcx.infer_shallow(self.ty_info.expr_tys[expr]);
+        let mut inner = ty;
+        while let TyKind::Ref(of, mutability) = inner.0 {
+            if *mutability == Mutability::Not {
+                return Err(self.mutate_shared_ref(ty, self.ast.exprs[place].span));
+            }
+            inner = self.tcx.infer_shallow(*of);
+        }
+        // a mutable reference can be written through regardless of where it's stored
+        if matches!(ty.0, TyKind::Ref(..)) { Ok(()) } else { self.check_mutable_place(expr) }
+    }
+
     fn enforce_valid_binop(
         &self,
         lhs: Ty<'tcx>,
@@ -731,7 +1139,10 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
             return Ok(());
         }
 
-        let lhs = self.tcx.infer_shallow(lhs);
+        let lhs = self
+            .tcx
+            .try_infer_shallow(lhs)
+            .map_err(|ty| self.cannot_infer(ty, self.ast.exprs[lhs_expr].span))?;
 
         if lhs.is_poison() || rhs.is_poison() {
             return Ok(());
@@ -741,7 +1152,14 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
             TyKind::Int => op.is_op_assign() | op.is_arithmetic() | op.is_compare() | op.is_range(),
             TyKind::Str => op.is_compare() | op.is_add(),
             TyKind::Bool => op.is_eq() | op.is_logical(),
-            TyKind::Char | TyKind::Unit => op.is_eq(),
+            TyKind::Char => op.is_eq() | op.is_range(),
+            TyKind::Unit | TyKind::Function(_) => op.is_eq(),
+            TyKind::Struct { .. } | TyKind::Tuple(_) if !op.is_eq() => {
+                op.is_compare() && self.is_ordered(lhs)
+            }
+            TyKind::Array(_) | TyKind::Struct { .. } | TyKind::Tuple(_) => {
+                op.is_eq() && self.is_comparable(lhs)
+            }
             _ => false,
         };
 
@@ -756,11 +1174,72 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         }
     }
 
-    fn index(&mut self, expr: ExprId, index: ExprId, span: Span) -> Result<Ty<'tcx>> {
+    // whether `==` is defined for `ty`, structs and arrays compare each of their elements
+    fn is_comparable(&self, ty: Ty<'tcx>) -> bool {
+        self.is_comparable_in(ty, &mut vec![])
+    }
+
+    // `visiting` holds the structs being checked, a struct containing itself is only as
+    // comparable as its other fields
+    fn is_comparable_in(&self, ty: Ty<'tcx>, visiting: &mut Vec<Ty<'tcx>>) -> bool {
+        let Ok(ty) = self.tcx.try_infer_shallow(ty) else { return true };
+        match ty.0 {
+            TyKind::Unit
+            | TyKind::Bool
+            | TyKind::Int
+            | TyKind::Char
+            | TyKind::Str
+            | TyKind::Function(_)
+            | TyKind::Poison => true,
+            TyKind::Array(of) | TyKind::Ref(of, _) => self.is_comparable_in(*of, visiting),
+            TyKind::Struct { .. } if visiting.contains(&ty) => true,
+            TyKind::Struct { .. } => {
+                visiting.push(ty);
+                let fields = self.tcx.struct_fields(ty);
+                let comparable = fields.iter().all(|&field| self.is_comparable_in(field, visiting));
+                visiting.pop();
+                comparable
+            }
+            TyKind::Tuple(fields) => {
+                fields.iter().all(|&field| self.is_comparable_in(field, visiting))
+            }
+            _ => false,
+        }
+    }
+
+    // whether `<` is defined for `ty`, structs and tuples compare their fields in order
+    fn is_ordered(&self, ty: Ty<'tcx>) -> bool {
+        self.is_ordered_in(ty, &mut vec![])
+    }
+
+    fn is_ordered_in(&self, ty: Ty<'tcx>, visiting: &mut Vec<Ty<'tcx>>) -> bool {
+        let Ok(ty) = self.tcx.try_infer_shallow(ty) else { return true };
+        match ty.0 {
+            TyKind::Int | TyKind::Char | TyKind::Str | TyKind::Poison => true,
+            TyKind::Struct { .. } if visiting.contains(&ty) => true,
+            TyKind::Struct { .. } => {
+                visiting.push(ty);
+                let fields = self.tcx.struct_fields(ty);
+                let ordered = fields.iter().all(|&field| self.is_ordered_in(field, visiting));
+                visiting.pop();
+                ordered
+            }
+            TyKind::Tuple(fields) => {
+                fields.iter().all(|&field| self.is_ordered_in(field, visiting))
+            }
+            _ => false,
+        }
+    }
+
+    fn index(&mut self, expr: ExprId, index: ExprId, id: ExprId) -> Result<Ty<'tcx>> {
         let expr = self.analyze_expr(expr)?;
-        let index = self.analyze_expr(index)?;
+        let index_ty = self.analyze_expr(index)?;
         let expr = self.tcx.infer_shallow(expr);
-        Ok(self.index_ty(expr, index, span))
+        // other types are indexed through their `Index` impl
+        if let Some(ty) = self.call_operator("Index", "index", expr, index_ty, index, id) {
+            return Ok(ty);
+        }
+        Ok(self.index_ty(expr, index_ty, self.ast.exprs[id].span))
     }
 
     fn index_ty(&mut self, lhs: Ty<'tcx>, rhs: Ty<'tcx>, span: Span) -> Ty<'tcx> {
@@ -770,7 +1249,7 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
             (TyKind::Array(_), TyKind::Range) => lhs,
             (TyKind::Array(of), TyKind::Int) => *of,
             (TyKind::Str, TyKind::Int) => Ty::CHAR,
-            (TyKind::Ref(lhs), _) => self.index_ty(*lhs, rhs, span),
+            (TyKind::Ref(lhs, _), _) => self.index_ty(*lhs, rhs, span),
             _ => {
                 self.errors.push(self.cannot_index(lhs, span));
                 Ty::POISON
@@ -791,14 +1270,18 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
     }
 
     fn analyze_fndecl(&mut self, decl: &FnDecl, id: ExprId) -> Result<Ty<'tcx>> {
+        let outer = (self.fn_generics, self.impl_generics);
         self.fn_generics = self.produced_generics[&id];
+        self.impl_generics = GenericRange::EMPTY;
         let block_id = decl.block.unwrap();
         // call `read_ident_raw` to avoid producing extra inference variables
         let (fn_ty, _) = self
             .read_ident_raw(decl.ident.symbol, Span::ZERO)
             .expect("fndecl ident should have been inserted already");
         let TyKind::Function(fn_ty) = fn_ty.0 else { unreachable!() };
-        self.fndecl_inner(&decl.params, block_id, fn_ty)
+        let out = self.fndecl_inner(&decl.params, block_id, fn_ty);
+        (self.fn_generics, self.impl_generics) = outer;
+        out
     }
 
     fn fndecl_inner(
@@ -818,17 +1301,43 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         Ok(Ty::UNIT)
     }
 
-    fn analyze_trait(&self, trait_: &Trait, id: ExprId) -> Result<Ty<'tcx>> {
-        _ = trait_;
-        _ = id;
-        todo!()
+    // closures get their own body, like fn items they cannot capture locals from the outside
+    fn analyze_closure(&mut self, params: &[ast::Param], body: ExprId) -> Result<Ty<'tcx>> {
+        let param_tys: ThinVec<_> = params
+            .iter()
+            .map(|param| match param.ty {
+                Some(ty) => self.read_ast_ty(ty),
+                None => self.tcx.new_infer(),
+            })
+            .collect();
+        let ret = self.tcx.new_infer();
+        let mut closure = Body::new(ret);
+        for (param, &ty) in std::iter::zip(params, &param_tys) {
+            closure.insert_var(param.ident, ty, Var::Let);
+        }
+        self.bodies.push(closure);
+        let body_ty = self.analyze_expr(body);
+        self.bodies.pop();
+        self.sub(body_ty?, ret, body);
+        Ok(self.tcx.intern(TyKind::Function(Function { params: param_tys, ret })))
     }
 
     fn analyze_impl(&mut self, impl_: &Impl, id: ExprId) -> Result<Ty<'tcx>> {
         _ = id;
-        let &Impl { ty, ref methods, .. } = impl_;
+        let &Impl { ty, ref methods, ref consts, .. } = impl_;
         self.impl_generics = self.produced_generics[&id];
         let ty = self.read_ast_ty(ty);
+        for &const_id in consts {
+            let ExprKind::Const { ident, expr, .. } = self.ast.exprs[const_id].kind else {
+                unreachable!()
+            };
+            // evaluated on every access, like the body of a function without parameters
+            let const_ty = self.tcx.get_const(ty, ident.symbol).unwrap();
+            self.bodies.push(Body::new(const_ty));
+            let expr_ty = self.analyze_expr(expr);
+            self.bodies.pop();
+            self.sub(expr_ty?, const_ty, expr);
+        }
         for &method_id in methods {
             let ExprKind::FnDecl(func) = &self.ast.exprs[method_id].kind else { unreachable!() };
             self.analyze_method(ty, func, method_id)?;
@@ -836,10 +1345,24 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         Ok(Ty::UNIT)
     }
 
-    fn read_ident(&self, ident: Symbol, span: Span) -> Result<Ty<'tcx>> {
-        Ok(match self.read_ident_raw(ident, span)? {
+    // `Type.NAME` reads an associated constant, returning the type it belongs to and its type
+    fn assoc_const(&self, expr: ExprId, field: Identifier) -> Option<(Ty<'tcx>, Ty<'tcx>)> {
+        let ExprKind::Ident(name) = self.ast.exprs[expr].kind else { return None };
+        let &owner = self.bodies.iter().rev().find_map(|body| body.ty_names.get(&name))?;
+        Some((owner, self.tcx.get_const(owner, field.symbol)?))
+    }
+
+    fn read_ident(&mut self, ident: Symbol, span: Span) -> Result<Ty<'tcx>> {
+        let var = self.read_ident_raw(ident, span)?;
+        let mut scopes = self.bodies.last().unwrap().scopes.iter().rev();
+        let scope = scopes.find(|scope| scope.variables.contains_key(&ident));
+        if let Some(decl) = scope.and_then(|scope| scope.decls.get(&ident)) {
+            self.unused.remove(decl);
+        }
+        Ok(match var {
             (Interned(TyKind::Function(func)), Var::Const) => {
-                self.tcx.intern(TyKind::Function(func.caller(self.tcx)))
+                let func = self.instantiate(func, span);
+                self.tcx.intern(TyKind::Function(func))
             }
             (other, _) => other,
         })
@@ -847,12 +1370,27 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
 
     // like `read_ident` but will not produce `TyVid`s for generic functions
     fn read_ident_raw(&self, ident: Symbol, span: Span) -> Result<(Ty<'tcx>, Var)> {
-        self.bodies
-            .iter()
-            .rev()
-            .find_map(|body| body.scopes.iter().rev().find_map(|scope| scope.variables.get(&ident)))
-            .copied()
-            .ok_or_else(|| self.ident_not_found(ident, span))
+        let (depth, var) = (self.bodies.iter().rev().enumerate())
+            .find_map(|(depth, body)| {
+                let mut scopes = body.scopes.iter().rev();
+                scopes.find_map(|scope| scope.variables.get(&ident)).map(|var| (depth, *var))
+            })
+            .ok_or_else(|| self.ident_not_found(ident, span))?;
+        // locals of an enclosing body are never captured
+        if depth != 0 && matches!(var.1, Var::Let) {
+            return Err(self.cannot_capture(ident, span));
+        }
+        Ok(var)
+    }
+
+    // the std function `function` names, if any. std is declared at the top level, where
+    // nothing can be redefined, so a local or nested function with the same name is never std's
+    fn std_fn(&self, function: ExprId) -> Option<Symbol> {
+        let ExprKind::Ident(ident) = self.ast.exprs[function].kind else { return None };
+        // the first scope is the top level one
+        let scopes = self.bodies[1..].iter().flat_map(|body| &body.scopes).enumerate();
+        let innermost = scopes.filter(|(_, scope)| scope.variables.contains_key(&ident)).last();
+        innermost.is_some_and(|(i, _)| i == 0).then_some(ident)
     }
 
     fn analyze_lit(&mut self, lit: &Lit) -> Result<Ty<'tcx>> {
@@ -868,6 +1406,13 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
             Lit::Int(..) => Ty::INT,
             Lit::Char(..) => Ty::CHAR,
             Lit::Str(..) => Ty::STR,
+            Lit::Tuple(elems) => {
+                let elems = elems
+                    .iter()
+                    .map(|&elem| self.analyze_expr(elem))
+                    .collect::<Result<ThinVec<_>>>()?;
+                self.tcx.intern(TyKind::Tuple(elems))
+            }
             Lit::Array { segments } => 'block: {
                 let mut segments = segments.iter();
                 let Some(first) = segments.next() else {
@@ -905,11 +1450,13 @@ impl<'tcx> Collector<'_, '_, 'tcx> {
         match self.ast.exprs[id].kind {
             ExprKind::Lit(ref lit) => match lit {
                 Lit::Bool(_) | Lit::Char(_) | Lit::Str(_) | Lit::Int(_) | Lit::Unit => true,
+                Lit::Tuple(elems) => elems.iter().all(|&elem| self.is_const(elem)),
                 Lit::Array { .. } => todo!(),
                 Lit::FStr(_) => todo!(),
             },
             ExprKind::Binary { lhs, rhs, .. } => self.is_const(lhs) && self.is_const(rhs),
             ExprKind::Unary { expr, .. } => self.is_const(expr),
+            ExprKind::Return(_) | ExprKind::Break(_) | ExprKind::Continue(_) => false,
             _ => todo!(),
         }
     }
@@ -60,6 +60,17 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
                     let expr = self.lower_fn_decl(Some(self.ty_info[impl_.ty]), decl);
                     block.push(self.hir.exprs.push(expr));
                 }
+                for &id in &impl_.consts {
+                    let ast::ExprKind::Const { ident, ty, expr } = self.ast.exprs[id].kind else {
+                        unreachable!()
+                    };
+                    let ret = ty.map_or(self.ty_info.expr_tys[expr], |ty| self.ty_info[ty]);
+                    let body = thin_vec![self.lower(expr)];
+                    let for_ty = Some(self.ty_info[impl_.ty]);
+                    let decl =
+                        hir::FnDecl { ident: ident.symbol, for_ty, params: vec![], ret, body };
+                    block.push(self.hir.exprs.push(decl.into()));
+                }
                 hir::ExprKind::Block(block).with(Ty::UNIT)
             }
             ast::ExprKind::Unreachable => ExprKind::Unreachable.with(Ty::NEVER),
@@ -86,6 +97,11 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
                 (hir::ExprKind::Assignment { lhs: self.lower(lhs), expr: self.lower(rhs) })
                     .with(expr_ty)
             }
+            ast::ExprKind::Binary { lhs, op, rhs }
+                if let Some(&fn_ty) = self.ty_info.method_types.get(&expr_id) =>
+            {
+                self.lower_overloaded_binop(lhs, op, rhs, fn_ty)
+            }
             ast::ExprKind::Binary { lhs, op, rhs } => {
                 let op = match op.kind {
                     BinOpKind::Add => hir::BinaryOp::Add,
@@ -111,15 +127,28 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
             ast::ExprKind::Block(block) => self.lower_block(block),
             ast::ExprKind::Lit(ref lit) => self.lower_literal(lit, expr_id),
             ast::ExprKind::FnDecl(ref decl) => self.lower_fn_decl(None, decl),
-            ast::ExprKind::Let { ident, expr, .. } => self.lower_let_stmt(ident.symbol, expr),
+            ast::ExprKind::Closure { ref params, body } => {
+                let TyKind::Function(func) = expr_ty.0 else { unreachable!() };
+                let params = std::iter::zip(params, &func.params)
+                    .map(|(param, &ty)| hir::Param { ident: param.ident.symbol, ty })
+                    .collect();
+                (ExprKind::Closure { params, body: self.lower(body) }).with(expr_ty)
+            }
+            ast::ExprKind::Let { ref binding, expr, .. } => self.lower_let_stmt(binding, expr),
             ast::ExprKind::Const { .. } => todo!(),
             ast::ExprKind::If { ref arms, els } => self.lower_if_stmt(arms, els, expr_id),
             ast::ExprKind::Match { scrutinee, ref arms } => {
                 self.lower_match(scrutinee, arms, expr_id)
             }
-            ast::ExprKind::While { condition, block } => self.lower_while_loop(condition, block),
-            ast::ExprKind::For { ident, iter, body } => {
-                self.lower_for_loop(ident.symbol, iter, body)
+            ast::ExprKind::While { condition, block, label } => {
+                self.lower_while_loop(condition, block, label.map(|label| label.symbol))
+            }
+            ast::ExprKind::Loop { block, label } => {
+                let body = self.lower_block_inner(block).1;
+                ExprKind::Loop { body, label: label.map(|label| label.symbol) }.with(expr_ty)
+            }
+            ast::ExprKind::For { ident, iter, body, label } => {
+                self.lower_for_loop(ident.symbol, iter, body, label.map(|label| label.symbol))
             }
             ast::ExprKind::Ident(symbol) => ExprKind::Ident(symbol).with(expr_ty),
             ast::ExprKind::FnCall { function, ref args } => {
@@ -141,7 +170,14 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
                     .hir
                     .exprs
                     .push((hir::ExprKind::Method { ty, method: method.symbol }).with(fn_ty));
-                (hir::ExprKind::FnCall { function: method, args: new_args }).with(expr_ty)
+                let span = self.ast.exprs[expr_id].span;
+                (hir::ExprKind::FnCall { function: method, args: new_args, span, std: None })
+                    .with(expr_ty)
+            }
+            ast::ExprKind::Index { expr, index }
+                if let Some(&fn_ty) = self.ty_info.method_types.get(&expr_id) =>
+            {
+                self.lower_operator_call("index", [expr, index], fn_ty)
             }
             ast::ExprKind::Index { expr, index } => (ExprKind::Index {
                 expr: self.lower(expr),
@@ -157,8 +193,12 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
                 ExprKind::Return(inner).with(Ty::NEVER)
             }
             ast::ExprKind::Unary { op, expr } => self.lower(expr).unary(op).with(expr_ty),
-            ast::ExprKind::Break => hir::Expr::BREAK,
-            ast::ExprKind::Continue => hir::Expr::CONTINUE,
+            ast::ExprKind::Break(label) => {
+                ExprKind::Break(label.map(|label| label.symbol)).with(Ty::NEVER)
+            }
+            ast::ExprKind::Continue(label) => {
+                ExprKind::Continue(label.map(|label| label.symbol)).with(Ty::NEVER)
+            }
             ast::ExprKind::Struct { ident, ref generics, ref fields } => {
                 _ = generics;
                 let struct_ty = self.ty_info.struct_types[&ident.span];
@@ -190,11 +230,31 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
                 let arms = thin_vec![IfStmt { condition, body }];
                 (hir::ExprKind::If { arms, els: ThinVec::new() }).with(Ty::UNIT)
             }
+            ast::ExprKind::FieldAccess { expr, field, .. }
+                if let Some(&fn_ty) = self.ty_info.method_types.get(&expr_id) =>
+            {
+                let ty = self.ty_info.expr_tys[expr];
+                let method = (hir::ExprKind::Method { ty, method: field.symbol }).with(fn_ty);
+                let function = self.hir.exprs.push(method);
+                let span = self.ast.exprs[expr_id].span;
+                (hir::ExprKind::FnCall { function, args: thin_vec![], span, std: None })
+                    .with(expr_ty)
+            }
             ast::ExprKind::FieldAccess { expr, field, .. } => {
-                let TyKind::Struct { symbols, .. } = self.get_ty(expr).0 else { unreachable!() };
-                let expr = self.lower(expr);
-
-                let field = symbols.iter().position(|&s| s == field.symbol).unwrap();
+                let mut ty = self.get_ty(expr);
+                let mut expr = self.lower(expr);
+                while let TyKind::Ref(of, _) = ty.0 {
+                    ty = *of;
+                    expr = self
+                        .hir
+                        .exprs
+                        .push((ExprKind::Unary { op: ast::UnaryOp::Deref, expr }).with(ty));
+                }
+                let field = match ty.0 {
+                    TyKind::Struct { .. } => self.ty_info.field_indices[&expr_id],
+                    TyKind::Tuple(_) => field.symbol.parse().unwrap(),
+                    _ => unreachable!(),
+                };
                 (hir::ExprKind::Field { expr, field }).with(expr_ty)
             }
         }
@@ -224,6 +284,48 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
         lhs
     }
 
+    // calls the operator's trait method, `a != b` becomes `!a.eq(b)`
+    fn lower_overloaded_binop(
+        &mut self,
+        lhs: ast::ExprId,
+        op: BinaryOp,
+        rhs: ast::ExprId,
+        fn_ty: Ty<'tcx>,
+    ) -> hir::Expr<'tcx> {
+        let method = match op.kind {
+            BinOpKind::Add => "add",
+            BinOpKind::Sub => "sub",
+            BinOpKind::Eq | BinOpKind::Neq => "eq",
+            _ => unreachable!("{op:?}"),
+        };
+        let call = self.lower_operator_call(method, [lhs, rhs], fn_ty);
+        if op.kind == BinOpKind::Neq {
+            self.hir.exprs.push(call).unary(hir::UnaryOp::Not).with(Ty::BOOL)
+        } else {
+            call
+        }
+    }
+
+    fn lower_operator_call(
+        &mut self,
+        method: &str,
+        operands: [ast::ExprId; 2],
+        fn_ty: Ty<'tcx>,
+    ) -> hir::Expr<'tcx> {
+        let TyKind::Function(Function { params, ret }) = fn_ty.0 else { unreachable!() };
+        let span = self.ast.spans(operands);
+        let args = operands.into_iter().zip(params).map(|(arg, &param)| {
+            let ty = self.get_ty(arg);
+            let arg = self.lower(arg);
+            self.make_eq_ref(arg, ty, param)
+        });
+        let args = args.collect();
+        let ty = params[0].fully_deref();
+        let method = hir::ExprKind::Method { ty, method: method.into() }.with(fn_ty);
+        let function = self.hir.exprs.push(method);
+        hir::ExprKind::FnCall { function, args, span, std: None }.with(*ret)
+    }
+
     fn lower_then_not(&mut self, ast_expr: ast::ExprId) -> hir::ExprId {
         let hir_expr = self.lower(ast_expr).unary(hir::UnaryOp::Not).with(Ty::BOOL);
         self.hir.exprs.push(hir_expr)
@@ -241,12 +343,19 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
         args: &[ast::ExprId],
         expr_id: ast::ExprId,
     ) -> hir::Expr<'tcx> {
+        let span = self.ast.exprs[expr_id].span;
         let function = self.lower(function);
         let args = args.iter().map(|arg| self.lower(*arg)).collect();
-        (hir::ExprKind::FnCall { function, args }).with(self.get_ty(expr_id))
+        let std = self.ty_info.std_calls.get(&expr_id).copied();
+        (hir::ExprKind::FnCall { function, args, span, std }).with(self.get_ty(expr_id))
     }
 
-    fn lower_while_loop(&mut self, condition: ast::ExprId, body: ast::BlockId) -> hir::Expr<'tcx> {
+    fn lower_while_loop(
+        &mut self,
+        condition: ast::ExprId,
+        body: ast::BlockId,
+        label: Option<Symbol>,
+    ) -> hir::Expr<'tcx> {
         let condition = self.lower_then_not(condition);
         let break_ = self.hir.exprs.push(hir::Expr::BREAK);
 
@@ -257,7 +366,7 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
         .with(Ty::UNIT);
         let mut block = self.lower_block_inner(body).1;
         block.insert(0, self.hir.exprs.push(if_stmt));
-        ExprKind::Loop(block).with(Ty::UNIT)
+        ExprKind::Loop { body: block, label }.with(Ty::UNIT)
     }
 
     fn lower_for_loop(
@@ -265,10 +374,11 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
         ident: Symbol,
         iter: ast::ExprId,
         body: ast::BlockId,
+        label: Option<Symbol>,
     ) -> hir::Expr<'tcx> {
         let iter = self.lower(iter);
         let body = self.lower_block_inner(body).1;
-        (hir::ExprKind::ForLoop { ident, iter, body }).with(Ty::UNIT)
+        (hir::ExprKind::ForLoop { ident, iter, body, label }).with(Ty::UNIT)
     }
 
     fn lower_if_stmt(
@@ -328,8 +438,8 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
         }
     }
 
-    fn lower_let_stmt(&mut self, ident: Symbol, expr: ast::ExprId) -> hir::Expr<'tcx> {
-        (hir::ExprKind::Let { ident, expr: self.lower(expr) }).with(Ty::UNIT)
+    fn lower_let_stmt(&mut self, binding: &ast::Binding, expr: ast::ExprId) -> hir::Expr<'tcx> {
+        (hir::ExprKind::Let { binding: binding.clone(), expr: self.lower(expr) }).with(Ty::UNIT)
     }
 
     fn lower_fn_decl(&mut self, for_ty: Option<Ty<'tcx>>, decl: &ast::FnDecl) -> hir::Expr<'tcx> {
@@ -365,6 +475,9 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
                 });
                 hir::Lit::Array { segments: hir_segments.collect() }
             }
+            ast::Lit::Tuple(ref elems) => {
+                hir::Lit::Tuple(elems.iter().map(|&elem| self.lower(elem)).collect())
+            }
             ast::Lit::FStr(ref segments) => {
                 let segments = segments.iter().map(|&segment| self.lower(segment)).collect();
                 hir::Lit::FStr { segments }
@@ -399,6 +512,10 @@ impl<'tcx> Lowering<'_, '_, 'tcx> {
         if block.is_expr {
             return false;
         }
-        block.stmts.last().is_some_and(|last| self.get_ty(*last) != Ty::UNIT)
+        // a diverging statement like `return` never reaches the unit after it
+        block.stmts.last().is_some_and(|last| {
+            let ty = self.get_ty(*last);
+            ty != Ty::UNIT && ty != Ty::NEVER
+        })
     }
 }
@@ -5,6 +5,7 @@ use clap::Parser;
 use crate::CodegenOpts;
 
 #[derive(Parser)]
+#[expect(clippy::struct_excessive_bools)]
 struct CliArgs {
     command: Command,
     path: PathBuf,
@@ -20,6 +21,8 @@ struct CliArgs {
     target: PathBuf,
     #[arg(short='C', long, action = clap::ArgAction::Append)]
     codegen: Vec<String>,
+    #[arg(long, help = "Fails compilation if any warnings are emitted")]
+    deny_warnings: bool,
 }
 
 pub struct Args {
@@ -29,6 +32,7 @@ pub struct Args {
     pub dump: Option<PathBuf>,
     pub show_auto: bool,
     pub codegen: CodegenOpts,
+    pub deny_warnings: bool,
 }
 
 impl Args {
@@ -46,6 +50,7 @@ impl Args {
             show_auto: args.show_auto,
             dump: args.dump.then_some(args.target),
             codegen: opts,
+            deny_warnings: args.deny_warnings,
         }
     }
 }
@@ -9,34 +9,166 @@ use miette::{Error, IntoDiagnostic};
 use petty_intern::Interner;
 
 use crate::{
-    Args, ast_analysis, ast_lowering, cli::Command, hir_lowering, mir_interpreter,
-    mir_optimizations, parse::parse, ty::TyCtx,
+    Args,
+    ast::Ast,
+    ast_analysis::{self, TyInfo},
+    ast_lowering,
+    cli::Command,
+    hir::Hir,
+    hir_lowering,
+    mir::Mir,
+    mir_interpreter::{self, RuntimeError},
+    mir_optimizations, parse, symbol,
+    ty::TyCtx,
 };
 
-#[cfg(test)]
-pub fn compile_test(path: impl Into<std::path::PathBuf>) -> Result<Vec<u8>, Vec<Error>> {
-    use crate::cli::Command;
+/// A source file with the standard library prepended, and the stages of the pipeline that
+/// need it. Each stage returns its output so it can be inspected before running the next.
+pub struct Source {
+    pub path: Option<PathBuf>,
+    pub src: String,
+}
+
+impl Source {
+    pub fn new(path: Option<PathBuf>, src: &str) -> Self {
+        Self { path, src: crate::STD.to_string() + src }
+    }
+    pub fn read(path: &Path) -> Result<Self, Vec<Error>> {
+        let src = fs::read_to_string(path).into_diagnostic().map_err(|e| vec![e])?;
+        Ok(Self::new(Some(path.to_path_buf()), &src))
+    }
+    pub fn parse(&self) -> Result<Ast, Vec<Error>> {
+        parse::parse(&self.src, self.path.as_deref())
+    }
+    pub fn analyze<'tcx>(
+        &self,
+        ast: &Ast,
+        tcx: &'tcx TyCtx<'tcx>,
+    ) -> Result<TyInfo<'tcx>, Vec<Error>> {
+        ast_analysis::analyze(self.path.as_deref(), &self.src, ast, tcx)
+    }
+    pub fn lower_hir<'tcx>(&self, ast: Ast, ty_info: TyInfo<'tcx>) -> Hir<'tcx> {
+        ast_lowering::lower(&self.src, self.path.as_deref(), ast, ty_info)
+    }
+    pub fn lower_mir<'tcx>(&self, hir: &Hir<'tcx>, tcx: &'tcx TyCtx<'tcx>) -> Mir {
+        hir_lowering::lower(hir, self.path.as_deref(), &self.src, tcx)
+    }
+}
 
-    let path = path.into();
-    let mut args = Args {
+// The arguments of `petty run` on a test file, without dumping anything.
+#[cfg(test)]
+pub fn test_args(path: impl Into<PathBuf>, optimize: bool) -> Args {
+    Args {
         show_auto: false,
         command: Command::Run,
-        path,
+        path: path.into(),
         verbose: 0,
         dump: None,
-        codegen: crate::CodegenOpts::all(true),
-    };
+        codegen: crate::CodegenOpts::all(optimize),
+        deny_warnings: false,
+    }
+}
+
+#[cfg(test)]
+pub fn compile_test(path: impl Into<PathBuf>) -> Result<Vec<u8>, Vec<Error>> {
+    let mut args = test_args(path, true);
     let mut w = vec![];
-    compile(&args, &mut w)?;
+    compile(&args, &mut w)?.code.unwrap_or_else(|err| panic!("{err}"));
     let mut w2 = Vec::with_capacity(w.len());
     args.codegen = crate::CodegenOpts::all(false);
-    compile(&args, &mut w2)?;
+    compile(&args, &mut w2)?.code.unwrap_or_else(|err| panic!("{err}"));
     assert_eq!(w, w2);
     Ok(w2)
 }
 
-pub fn compile(args: &Args, w: &mut dyn Write) -> miette::Result<(), Vec<Error>> {
-    let src = fs::read_to_string(&args.path).into_diagnostic().map_err(|e| vec![e])?;
+// Runs the pipeline up to the hir, or the mir when asked for, for tests of a single stage.
+#[cfg(test)]
+impl Source {
+    pub fn with_hir<R>(
+        &self,
+        f: impl for<'tcx> FnOnce(Hir<'tcx>, &'tcx TyCtx<'tcx>) -> R,
+    ) -> Result<R, Vec<Error>> {
+        let ty_intern = Interner::default();
+        let tcx = TyCtx::new(&ty_intern);
+        let ast = self.parse()?;
+        let analysis = self.analyze(&ast, &tcx)?;
+        Ok(f(self.lower_hir(ast, analysis), &tcx))
+    }
+    pub fn mir(&self, optimize: bool) -> Result<Mir, Vec<Error>> {
+        self.with_hir(|hir, tcx| {
+            let mut mir = self.lower_mir(&hir, tcx);
+            mir_optimizations::optimize(&mut mir, &crate::CodegenOpts::all(optimize), 0);
+            mir
+        })
+    }
+}
+
+#[cfg(test)]
+pub fn compile_hir_test<R>(
+    path: impl AsRef<Path>,
+    f: impl for<'tcx> FnOnce(&Hir<'tcx>) -> R,
+) -> Result<R, Vec<Error>> {
+    Source::read(path.as_ref())?.with_hir(|hir, _| f(&hir))
+}
+
+#[cfg(test)]
+pub fn compile_mir_test(path: impl AsRef<Path>) -> Result<Mir, Vec<Error>> {
+    Source::read(path.as_ref())?.mir(true)
+}
+
+#[cfg(test)]
+pub fn hir_from_src<R>(
+    src: &str,
+    f: impl for<'tcx> FnOnce(&Hir<'tcx>, &'tcx TyCtx<'tcx>) -> R,
+) -> R {
+    Source::new(None, src).with_hir(|hir, tcx| f(&hir, tcx)).unwrap()
+}
+
+#[cfg(test)]
+pub fn mir_from_src(src: &str, optimize: bool) -> Mir {
+    Source::new(None, src).mir(optimize).unwrap()
+}
+
+// The hir as displayed, without the items from std, for snapshot tests.
+#[cfg(test)]
+pub fn hir_snapshot(path: impl AsRef<Path>) -> Result<String, Vec<Error>> {
+    let std_items = Source::new(None, "").parse()?.top_level.len();
+    Source::read(path.as_ref())?.with_hir(|mut hir, tcx| {
+        hir.root.drain(..std_items);
+        format!("{}", hir.display(tcx))
+    })
+}
+
+// The optimized mir as displayed, without auto-generated bodies, for snapshot tests.
+// Abort messages are rendered reports, so only their first line is kept to not depend on how
+// the report is drawn.
+#[cfg(test)]
+pub fn mir_snapshot(path: impl AsRef<Path>) -> Result<String, Vec<Error>> {
+    let mut mir = compile_mir_test(path)?;
+    for block in mir.bodies.iter_mut().flat_map(|body| &mut body.blocks) {
+        if let crate::mir::Terminator::Abort { msg } = &mut block.terminator {
+            let title = msg.lines().next().unwrap_or_default();
+            *msg = title.trim_start_matches([' ', '×']).into();
+        }
+    }
+    Ok(mir.display(false).to_string())
+}
+
+/// A program that compiled, along with the warnings found while compiling it.
+#[derive(Debug)]
+pub struct Compiled {
+    /// The exit code if the program was run, a runtime error is returned as-is for the caller
+    /// to decide how to report it.
+    pub code: Result<i32, RuntimeError>,
+    pub warnings: Vec<Error>,
+}
+
+// compile errors and warnings are returned as diagnostics for the caller to render
+pub fn compile(args: &Args, w: &mut dyn Write) -> miette::Result<Compiled, Vec<Error>> {
+    if args.verbose > 1 {
+        symbol::record_interned();
+    }
+    let source = Source::read(&args.path)?;
     if let Some(target) = &args.dump {
         create_new_dir(target).into_diagnostic().map_err(|e| vec![e])?;
     }
@@ -61,33 +193,43 @@ pub fn compile(args: &Args, w: &mut dyn Write) -> miette::Result<(), Vec<Error>>
         };
     }
     let start = Instant::now();
-    let src = crate::STD.to_string() + &src;
-    let ast = parse(&src, Some(&args.path)).map_err(|e| vec![e])?;
+    let ast = source.parse()?;
     dump!(ast);
-    let analysis = ast_analysis::analyze(Some(&args.path), &src, &ast, &tcx)?;
-    let hir = ast_lowering::lower(&src, Some(&args.path), ast, analysis);
+    let mut analysis = source.analyze(&ast, &tcx)?;
+    let mut warnings = std::mem::take(&mut analysis.warnings);
+    if args.deny_warnings && !warnings.is_empty() {
+        let count = warnings.len();
+        warnings.push(miette::miette!("aborting due to {count} warning(s) (`--deny-warnings`)"));
+        return Err(warnings);
+    }
+    let hir = source.lower_hir(ast, analysis);
     dump!(@d hir);
-    let mut mir = hir_lowering::lower(&hir, Some(&args.path), &src, &tcx);
+    let mut mir = source.lower_mir(&hir, &tcx);
     drop(hir);
     mir_optimizations::optimize(&mut mir, &args.codegen, args.verbose);
     dump!(mir, mir.display(args.show_auto).to_string());
     if args.verbose > 1 {
         crate::log!("type interner entries: {}", ty_intern.len());
+        crate::log!("symbols interned: {}", symbol::interned_count());
+    }
+    if args.verbose > 2 {
+        crate::log!("{:?}", symbol::interned());
     }
     if args.verbose > 0 {
         crate::log!("compile time: {:?}", start.elapsed());
     }
+    let mut code = Ok(0);
     if args.command == Command::Run {
         if args.verbose > 0 {
             crate::log!();
         }
-        mir_interpreter::interpret(&mir, w);
+        code = mir_interpreter::interpret(&mir, w);
         if args.verbose > 0 {
             crate::log!();
             crate::log!("total time: {:?}", start.elapsed());
         }
     }
-    Ok(())
+    Ok(Compiled { code, warnings })
 }
 
 fn create_new_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
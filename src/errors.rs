@@ -1,6 +1,6 @@
 use std::path::Path;
 
-use miette::{Error, LabeledSpan, NamedSource};
+use miette::{Error, LabeledSpan, NamedSource, Severity};
 
 use crate::span::Span;
 
@@ -15,6 +15,26 @@ pub fn error<S: Into<String>>(
     error_with(error, path, src, labels, None)
 }
 
+/// A type mismatch, with the primary label on the value that was found
+/// and a secondary label on whatever set the expectation.
+#[inline(never)]
+#[cold]
+pub fn mismatch(
+    path: Option<&Path>,
+    src: &str,
+    found: (Span, impl Into<String>),
+    expected: (Span, impl Into<String>),
+) -> Error {
+    let labels = vec![
+        LabeledSpan::new_primary_with_span(
+            Some(found.1.into()),
+            offset_span(found.0).into_range_usize(),
+        ),
+        LabeledSpan::at(offset_span(expected.0).into_range_usize(), expected.1),
+    ];
+    error_inner("mismatched types", path, src, labels, None, Severity::Error)
+}
+
 #[inline(never)]
 #[cold]
 pub fn error_with<S: Into<String>>(
@@ -28,7 +48,23 @@ pub fn error_with<S: Into<String>>(
         .into_iter()
         .map(|(span, msg)| LabeledSpan::at(offset_span(span).into_range_usize(), msg))
         .collect();
-    error_inner(error, path, src, labels, help)
+    error_inner(error, path, src, labels, help, Severity::Error)
+}
+
+/// Like `error`, but the diagnostic is reported without stopping compilation.
+#[inline(never)]
+#[cold]
+pub fn warning<S: Into<String>>(
+    warning: &str,
+    path: Option<&Path>,
+    src: &str,
+    labels: impl IntoIterator<Item = (Span, S)>,
+) -> Error {
+    let labels: Vec<_> = labels
+        .into_iter()
+        .map(|(span, msg)| LabeledSpan::at(offset_span(span).into_range_usize(), msg))
+        .collect();
+    error_inner(warning, path, src, labels, None, Severity::Warning)
 }
 
 #[inline(never)]
@@ -39,11 +75,13 @@ fn error_inner(
     src: &str,
     labels: Vec<LabeledSpan>,
     extra: Option<&str>,
+    severity: Severity,
 ) -> Error {
     let suggest = extra.map(str::to_string);
     miette::Report::from({
         let mut diag = miette::MietteDiagnostic::new(error.to_string());
         diag.help = suggest;
+        diag.severity = Some(severity);
         diag.labels = Some(labels);
         diag
     })
@@ -3,7 +3,7 @@ use std::{
     mem,
 };
 
-use super::{ArraySeg, ExprKind, FnDecl, MatchArm, OpAssign, Param, Pat};
+use super::{ArraySeg, Binding, ExprKind, FnDecl, MatchArm, OpAssign, Param, Pat};
 use crate::{
     hir::{BinaryOp, ExprId, Hir, Lit, UnaryOp},
     symbol::Symbol,
@@ -54,13 +54,15 @@ impl Writer<'_, '_> {
                 self.indent -= 1;
                 (Line, "}").write(self);
             }
-            ExprKind::Loop(ref block) => ("loop ", block.as_slice()).write(self),
+            ExprKind::Loop { ref body, label } => {
+                (label.map(|label| (label, ": ")), "loop ", body.as_slice()).write(self);
+            }
             ExprKind::StructInit => "<struct init>".write(self),
             ExprKind::Assignment { lhs, expr } => (lhs, " = ", expr).write(self),
             ExprKind::Abort { msg } => ("abort(", msg, ")").write(self),
             ExprKind::Unreachable => "unreachable".write(self),
-            ExprKind::Break => "break".write(self),
-            ExprKind::Continue => "continue".write(self),
+            ExprKind::Break(label) => ("break", label.map(|label| (" ", label))).write(self),
+            ExprKind::Continue(label) => ("continue", label.map(|label| (" ", label))).write(self),
             ExprKind::Return(expr) => ("return ", expr).write(self),
             ExprKind::Literal(ref lit) => lit.write(self),
             ExprKind::Binary { lhs, op, rhs } => {
@@ -70,7 +72,7 @@ impl Writer<'_, '_> {
             ExprKind::OpAssign { place, op, expr } => (place, op, expr).write(self),
             ExprKind::Ident(ident) => ident.write(self),
             ExprKind::Method { ty, method } => (ty, "::", method).write(self),
-            ExprKind::FnCall { function, ref args } => {
+            ExprKind::FnCall { function, ref args, .. } => {
                 (function, "(", Sep(args, ", "), ")").write(self);
             }
             ExprKind::Index { expr, index, .. } => (expr, "[", index, "]").write(self),
@@ -93,10 +95,13 @@ impl Writer<'_, '_> {
                 )
                     .write(self);
             }
-            ExprKind::Let { ident, expr } => {
+            ExprKind::Closure { ref params, body } => {
+                ("|", Sep(params, ", "), "| ", body).write(self);
+            }
+            ExprKind::Let { ref binding, expr } => {
                 self.inside_expr = inside_expr;
                 let ty = self.hir.exprs[expr].ty;
-                ("let ", ident, (": ", ty), " = ").write(self);
+                ("let ", binding, (": ", ty), " = ").write(self);
                 self.inside_expr = false;
                 expr.write(self);
             }
@@ -114,9 +119,10 @@ impl Writer<'_, '_> {
                 }
                 (!els.is_empty()).then_some(("else ", els.as_slice())).write(self);
             }
-            ExprKind::ForLoop { ident, iter, ref body } => {
+            ExprKind::ForLoop { ident, iter, ref body, label } => {
                 self.inside_expr = inside_expr;
-                ("for ", ident, " in ", iter, body.as_slice()).write(self);
+                let label = label.map(|label| (label, ": "));
+                (label, "for ", ident, " in ", iter, body.as_slice()).write(self);
             }
         }
         self.inside_expr = inside_expr;
@@ -176,6 +182,7 @@ impl Dump for Lit {
             Self::String(str) => _ = write!(w.f, "{:?}", &**str),
             Self::Char(char) => _ = write!(w.f, "{char:?}"),
             Self::Array { segments } => ("[", Sep(segments, ", "), "]").write(w),
+            Self::Tuple(elems) => ("(", Sep(elems, ", "), ")").write(w),
             Self::FStr { segments } => FStr(segments).write(w),
         }
     }
@@ -200,6 +207,15 @@ impl Dump for FStr<'_> {
     }
 }
 
+impl Dump for Binding {
+    fn write(&self, w: &mut Writer) {
+        match self {
+            Self::Ident(ident) => ident.symbol.write(w),
+            Self::Tuple(elems) => ("(", Sep(elems, ", "), ")").write(w),
+        }
+    }
+}
+
 impl Dump for Param<'_> {
     fn write(&self, w: &mut Writer) {
         (self.ident, ": ", self.ty).write(w);
@@ -259,6 +275,7 @@ impl Dump for UnaryOp {
             Self::Not => "!",
             Self::Neg => "-",
             Self::Ref => "&",
+            Self::RefMut => "&mut ",
             Self::Deref => "*",
         });
     }
@@ -21,8 +21,7 @@ pub struct Expr<'tcx> {
 
 impl Expr<'_> {
     pub const UNIT: Self = ExprKind::Literal(Lit::Unit).with(Ty::UNIT);
-    pub const BREAK: Self = ExprKind::Break.with(Ty::NEVER);
-    pub const CONTINUE: Self = ExprKind::Continue.with(Ty::NEVER);
+    pub const BREAK: Self = ExprKind::Break(None).with(Ty::NEVER);
 }
 
 #[derive(Debug)]
@@ -39,16 +38,17 @@ pub enum ExprKind<'tcx> {
     Literal(Lit),
     Block(ThinVec<ExprId>),
     Method { ty: Ty<'tcx>, method: Symbol },
-    FnCall { function: ExprId, args: ThinVec<ExprId> },
+    FnCall { function: ExprId, args: ThinVec<ExprId>, span: Span, std: Option<Symbol> },
     Index { expr: ExprId, index: ExprId, span: Span },
     FnDecl(Box<FnDecl<'tcx>>),
-    Let { ident: Symbol, expr: ExprId },
+    Closure { params: Vec<Param<'tcx>>, body: ExprId },
+    Let { binding: Binding, expr: ExprId },
     If { arms: ThinVec<IfStmt>, els: ThinVec<ExprId> },
     Match { scrutinee: ExprId, arms: ThinVec<MatchArm> },
-    Loop(ThinVec<ExprId>),
-    ForLoop { ident: Symbol, iter: ExprId, body: ThinVec<ExprId> },
-    Break,
-    Continue,
+    Loop { body: ThinVec<ExprId>, label: Option<Symbol> },
+    ForLoop { ident: Symbol, iter: ExprId, body: ThinVec<ExprId>, label: Option<Symbol> },
+    Break(Option<Symbol>),
+    Continue(Option<Symbol>),
     Return(ExprId),
 }
 
@@ -121,6 +121,7 @@ pub enum OpAssign {
 }
 
 pub type UnaryOp = crate::ast::UnaryOp;
+pub type Binding = crate::ast::Binding;
 
 #[derive(Debug)]
 pub enum Lit {
@@ -130,6 +131,7 @@ pub enum Lit {
     Char(char),
     String(Symbol),
     Array { segments: ThinVec<ArraySeg> },
+    Tuple(ThinVec<ExprId>),
     FStr { segments: ThinVec<ExprId> },
 }
 
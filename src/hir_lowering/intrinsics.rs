@@ -2,6 +2,7 @@ use thin_vec::ThinVec;
 
 use super::Lowering;
 use crate::{
+    hir::ExprId,
     mir::{BinaryOp, Constant, Operand, Place, RValue, Terminator, UnaryOp},
     symbol::Symbol,
     ty::{Ty, TyKind},
@@ -37,6 +38,33 @@ impl Lowering<'_, '_, '_> {
         }
     }
 
+    // Direct calls to `print` and `println` format their arguments in place, based on their type,
+    // instead of going through an instance of the generic wrapper for every type printed.
+    // Several arguments are printed separated by a single space.
+    pub fn lower_print(&mut self, op: UnaryOp, args: &[ExprId]) -> RValue {
+        let mut segments = vec![];
+        for (i, &arg) in args.iter().enumerate() {
+            if i != 0 {
+                segments.push(Constant::Str(arcstr::literal!(" ")).into());
+            }
+            let formatted = self.format_expr(arg);
+            segments.push(self.process(formatted, Ty::STR));
+        }
+        let operand = match <[_; 1]>::try_from(segments) {
+            Ok([single]) => single,
+            Err(segments) => self.process(RValue::StrJoin(segments), Ty::STR),
+        };
+        RValue::Unary { op, operand }
+    }
+
+    // Direct calls to `concat` join all of their arguments at once.
+    pub fn lower_concat(&mut self, args: &[ExprId]) -> RValue {
+        RValue::StrJoin(args.iter().map(|&arg| self.lower(arg)).collect())
+    }
+
+    // Intrinsics are declared in `std.pty` with an `unreachable` body. Direct calls are replaced
+    // with the matching rvalue, and the declared body is lowered to the same rvalue so the
+    // function can still be called indirectly. Names missing here keep their `unreachable` body.
     fn intrinsic_rvalue(name: &str, ty: Option<Ty>, args: &[Operand]) -> Option<RValue> {
         macro_rules! arg {
             ($n: literal) => {
@@ -55,16 +83,32 @@ impl Lowering<'_, '_, '_> {
             };
         }
         Some(match (ty.map(|ty| ty.0), name) {
-            (Some(TyKind::Str), "len") => unary!(StrLen),
+            (Some(TyKind::Str), "len" | "byte_len") => unary!(StrLen),
+            (Some(TyKind::Str), "char_len") => unary!(StrCharLen),
             (Some(TyKind::Str), "find") => binary!(StrFind),
             (Some(TyKind::Str), "rfind") => binary!(StrRFind),
             (Some(TyKind::Int), "chr") => unary!(Chr),
             (Some(TyKind::Char), "ord") => unary!(Ord),
             (None, "__strjoin") => unary!(StrJoin),
             (None, "__printstr") => unary!(Print),
+            (None, "__printlnstr") => unary!(Println),
             (None, "__arraylen") => unary!(ArrayLen),
             (None, "__arraypush") => binary!(ArrayPush),
             (None, "__arraypop") => unary!(ArrayPop),
+            (None, "sort") => unary!(ArraySort),
+            (None, "reverse") => unary!(ArrayReverse),
+            (None, "contains") => binary!(ArrayContains),
+            (None, "to_hex") => unary!(IntToHex),
+            (None, "to_bin") => unary!(IntToBin),
+            (None, "concat") => RValue::StrJoin(vec![arg!(0)]),
+            (None, "map_new") => RValue::Use(Constant::EmptyMap.into()),
+            (None, "__mapentry") => binary!(MapEntry),
+            (None, "map_get") => binary!(MapGet),
+            (None, "map_contains") => binary!(MapContains),
+            (None, "set_new") => RValue::Use(Constant::EmptySet.into()),
+            (None, "set_add") => binary!(SetAdd),
+            (None, "set_contains") => binary!(SetContains),
+            (None, "set_len") => unary!(SetLen),
             _ => return None,
         })
     }
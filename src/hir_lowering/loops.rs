@@ -1,14 +1,15 @@
 use std::mem;
 
 use super::{
-    BinaryOp, BlockId, Constant, ExprId, Local, Lowering, Operand, Place, RValue, Symbol,
-    Terminator, UnaryOp,
+    BinaryOp, BlockId, Constant, ExprId, Local, LoopLabel, Lowering, Operand, Place, RValue,
+    Symbol, Terminator, UnaryOp,
 };
-use crate::mir::Projection;
+use crate::{mir::Projection, ty::TyKind};
 
 impl Lowering<'_, '_, '_> {
     pub fn lower_loop(
         &mut self,
+        label: Option<Symbol>,
         condition: impl FnOnce(&mut Self) -> Option<Local>,
         iter: impl FnOnce(&mut Self),
     ) {
@@ -18,6 +19,10 @@ impl Lowering<'_, '_, '_> {
         let prev_loop = mem::take(&mut self.current_mut().breaks);
         self.current_mut().breaks.push(condition_block);
         let prev_continue = self.current_mut().continue_block.replace(condition_block);
+        if let Some(label) = label {
+            let continue_block = condition_block;
+            self.current_mut().labels.push(LoopLabel { label, continue_block, breaks: vec![] });
+        }
 
         let to_fix = condition(self).map(|looping| {
             let next = self.current_block() + 1;
@@ -41,7 +46,8 @@ impl Lowering<'_, '_, '_> {
 
         let breaks = mem::replace(&mut self.current_mut().breaks, prev_loop);
         self.current_mut().continue_block = prev_continue;
-        for block in breaks {
+        let labelled = label.map(|_| self.current_mut().labels.pop().unwrap());
+        for block in breaks.into_iter().chain(labelled.into_iter().flat_map(|label| label.breaks)) {
             self.body_mut().blocks[block].terminator.complete(after_block);
         }
     }
@@ -50,10 +56,12 @@ impl Lowering<'_, '_, '_> {
         &mut self,
         ident: Symbol,
         body: &[ExprId],
+        label: Option<Symbol>,
         condition: impl FnOnce(&mut Self) -> Local,
         iter: impl FnOnce(&mut Self) -> Local,
     ) {
         self.lower_loop(
+            label,
             |lower| Some(condition(lower)),
             |lower| {
                 let ident_var = iter(lower);
@@ -65,7 +73,14 @@ impl Lowering<'_, '_, '_> {
         );
     }
 
-    pub fn range_for(&mut self, ident: Symbol, iter: ExprId, body: &[ExprId]) {
+    pub fn range_for(
+        &mut self,
+        ident: Symbol,
+        iter: ExprId,
+        body: &[ExprId],
+        label: Option<Symbol>,
+    ) {
+        let chars = matches!(self.ty(iter).0, TyKind::CharRange);
         let range = self.lower(iter);
         let lo = self.assign_new(RValue::Unary { op: UnaryOp::RangeStart, operand: range.clone() });
         let hi = self.assign_new(RValue::Unary { op: UnaryOp::RangeEnd, operand: range });
@@ -73,6 +88,7 @@ impl Lowering<'_, '_, '_> {
         self.for_loop(
             ident,
             body,
+            label,
             |lower| {
                 lower.assign_new(RValue::Binary {
                     lhs: Operand::local(lo),
@@ -81,7 +97,12 @@ impl Lowering<'_, '_, '_> {
                 })
             },
             |lower| {
-                let ident_var = lower.assign_new(Operand::local(lo));
+                let ident_var = if chars {
+                    lower
+                        .assign_new(RValue::Unary { op: UnaryOp::Chr, operand: Operand::local(lo) })
+                } else {
+                    lower.assign_new(Operand::local(lo))
+                };
                 lower.assign(
                     lo,
                     RValue::Binary {
@@ -95,7 +116,13 @@ impl Lowering<'_, '_, '_> {
         );
     }
 
-    pub fn array_for(&mut self, ident: Symbol, iter: ExprId, body: &[ExprId]) {
+    pub fn array_for(
+        &mut self,
+        ident: Symbol,
+        iter: ExprId,
+        body: &[ExprId],
+        label: Option<Symbol>,
+    ) {
         let iter_rvalue = self.lower_rvalue(iter);
         let iter = self.assign_new(iter_rvalue);
 
@@ -108,6 +135,7 @@ impl Lowering<'_, '_, '_> {
         self.for_loop(
             ident,
             body,
+            label,
             |lower| {
                 lower.assign_new(RValue::Binary {
                     lhs: Operand::local(lo),
@@ -2,14 +2,10 @@ mod intrinsics;
 mod loops;
 mod pattern;
 
-use std::{
-    collections::{BTreeMap, VecDeque},
-    mem,
-    path::Path,
-};
+use std::{collections::VecDeque, mem, path::Path};
 
 use arcstr::ArcStr;
-use index_vec::IndexVec;
+use thin_vec::ThinVec;
 
 use crate::{
     HashMap, errors,
@@ -20,7 +16,7 @@ use crate::{
     },
     source::span::Span,
     symbol::Symbol,
-    ty::{self, GenericId, StructId, Ty, TyCtx, TyKey, TyKind},
+    ty::{self, GenericId, MethodMap, Ty, TyCtx, TyKind},
 };
 
 pub fn lower<'tcx>(hir: &Hir<'tcx>, path: Option<&Path>, src: &str, tcx: &'tcx TyCtx<'tcx>) -> Mir {
@@ -33,9 +29,9 @@ pub fn lower<'tcx>(hir: &Hir<'tcx>, path: Option<&Path>, src: &str, tcx: &'tcx T
         hir,
         mir,
         bodies,
-        struct_display_bodies: IndexVec::default(),
+        struct_display_bodies: HashMap::default(),
         array_display_bodies: HashMap::default(),
-        methods: BTreeMap::default(),
+        methods: MethodMap::default(),
         strings: HashMap::default(),
         src,
         path,
@@ -43,6 +39,7 @@ pub fn lower<'tcx>(hir: &Hir<'tcx>, path: Option<&Path>, src: &str, tcx: &'tcx T
         mono_generics: VecDeque::default(),
         generic_map: None,
     };
+    lowering.declare_items(&hir.root);
     for &expr in &hir.root {
         lowering.lower(expr);
     }
@@ -51,14 +48,16 @@ pub fn lower<'tcx>(hir: &Hir<'tcx>, path: Option<&Path>, src: &str, tcx: &'tcx T
     lowering.mir
 }
 
+// the maps below are only ever looked up, never iterated, so bodies are emitted in the order
+// they're reached and the mir does not depend on how types or symbols hash.
 struct Lowering<'hir, 'tcx, 'src> {
     tcx: &'tcx TyCtx<'tcx>,
     hir: &'hir Hir<'tcx>,
     mir: Mir,
     bodies: Vec<BodyInfo>,
-    struct_display_bodies: IndexVec<StructId, Option<BodyId>>,
+    struct_display_bodies: HashMap<Ty<'tcx>, BodyId>,
     array_display_bodies: HashMap<Ty<'tcx>, BodyId>,
-    methods: BTreeMap<(TyKey<'tcx>, Symbol), BodyId>,
+    methods: MethodMap<'tcx, BodyId>,
     strings: HashMap<Symbol, ArcStr>,
     src: &'src str,
     path: Option<&'src Path>,
@@ -85,16 +84,31 @@ macro_rules! str {
 struct BodyInfo {
     body: BodyId,
     functions: HashMap<Symbol, BodyId>,
+    // functions registered before their declaration is reached, so they can be called from above it
+    declared: HashMap<ExprId, BodyId>,
     stmts: Vec<Statement>,
     breaks: Vec<BlockId>,
     continue_block: Option<BlockId>,
+    // the labelled loops being lowered, innermost last
+    labels: Vec<LoopLabel>,
     scopes: Vec<Scope>,
 }
 
+struct LoopLabel {
+    label: Symbol,
+    continue_block: BlockId,
+    breaks: Vec<BlockId>,
+}
+
 impl BodyInfo {
     pub fn scope(&mut self) -> &mut Scope {
         self.scopes.last_mut().unwrap()
     }
+
+    // analysis has already checked that the label belongs to an enclosing loop
+    pub fn label(&mut self, label: Symbol) -> &mut LoopLabel {
+        self.labels.iter_mut().rev().find(|loop_| loop_.label == label).unwrap()
+    }
 }
 
 #[derive(Debug, Default)]
@@ -107,15 +121,17 @@ impl BodyInfo {
         Self {
             body,
             functions: HashMap::default(),
+            declared: HashMap::default(),
             scopes: vec![Scope::default()],
             stmts: vec![],
             breaks: vec![],
             continue_block: None,
+            labels: vec![],
         }
     }
 }
 
-impl<'tcx> Lowering<'_, 'tcx, '_> {
+impl<'hir, 'tcx> Lowering<'hir, 'tcx, '_> {
     fn ty(&self, id: ExprId) -> Ty<'tcx> {
         self.mono(self.hir.exprs[id].ty)
     }
@@ -123,13 +139,25 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
         match ty.0 {
             TyKind::Generic(id) => self.generic_map.as_ref().unwrap()[id],
             TyKind::Array(of) => self.tcx.intern(TyKind::Array(self.mono(*of))),
+            TyKind::Set(of) => self.tcx.intern(TyKind::Set(self.mono(*of))),
+            TyKind::Map(key, value) => {
+                self.tcx.intern(TyKind::Map(self.mono(*key), self.mono(*value)))
+            }
             TyKind::Function(ty::Function { params, ret }) => {
                 let params = params.iter().map(|param| self.mono(*param)).collect();
                 let ret = self.mono(*ret);
                 self.tcx.intern(TyKind::Function(ty::Function { params, ret }))
             }
-            TyKind::Ref(of) => self.tcx.intern(TyKind::Ref(self.mono(*of))),
-            // TyKind::Struct { .. } => todo!(),
+            TyKind::Ref(of, mutability) => {
+                self.tcx.intern(TyKind::Ref(self.mono(*of), *mutability))
+            }
+            TyKind::Tuple(elems) => {
+                self.tcx.intern(TyKind::Tuple(elems.iter().map(|elem| self.mono(*elem)).collect()))
+            }
+            TyKind::Struct { id, generics } => {
+                let generics = generics.iter().map(|generic| self.mono(*generic)).collect();
+                self.tcx.intern(TyKind::Struct { id: *id, generics })
+            }
             _ => ty,
         }
     }
@@ -224,6 +252,10 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
 
     fn assign(&mut self, place: impl Into<Place>, rvalue: impl Into<RValue>) {
         let rvalue = rvalue.into();
+        // the value of a diverging expression is never produced, so there is nothing to store
+        if rvalue.is_unreachable() {
+            return;
+        }
         let place = place.into();
         self.current_mut().stmts.push(Statement::Assign { place, rvalue });
     }
@@ -239,21 +271,21 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
         let is_unit = self.ty(id).is_unit();
 
         match self.hir.exprs[id].kind {
-            ExprKind::ForLoop { ident, iter, ref body } => {
+            ExprKind::ForLoop { ident, iter, ref body, label } => {
                 match self.ty(iter).0 {
-                    TyKind::Range => self.range_for(ident, iter, body),
-                    TyKind::Array(..) => self.array_for(ident, iter, body),
+                    TyKind::Range | TyKind::CharRange => self.range_for(ident, iter, body, label),
+                    TyKind::Array(..) => self.array_for(ident, iter, body, label),
                     _ => unreachable!(),
                 }
                 RValue::UNIT
             }
             ExprKind::Unreachable => {
                 let _ = self.finish_with(Terminator::Unreachable);
-                RValue::UNIT
+                RValue::UNREACHABLE
             }
             ExprKind::Abort { msg } => {
                 let _ = self.finish_with(Terminator::Abort { msg });
-                RValue::UNIT
+                RValue::UNREACHABLE
             }
             ExprKind::Field { expr, field } => {
                 let local = self.lower_local(expr);
@@ -275,65 +307,48 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
             }
             ExprKind::Literal(ref lit) => self.lit_rvalue(lit),
             ExprKind::FnDecl(ref decl) => {
-                let hir::FnDecl { ident, for_ty, ref params, ref body, .. } = **decl;
-
                 assert!(self.current_mut().stmts.is_empty(), "TODO");
 
-                let is_generic = decl.is_generic();
-
-                let body_id = self
-                    .mir
-                    .bodies
-                    .push(Body::new(Some(ident), params.len()).with_auto(is_generic));
-
-                if is_generic {
-                    self.generic_fns
-                        .insert(body_id, GenericFns { decl, impls: HashMap::default() });
-                }
-
-                match for_ty {
-                    Some(ty) => _ = self.methods.insert((TyKey(ty), ident), body_id),
-                    None => _ = self.current_mut().functions.insert(ident, body_id),
-                }
+                let body_id = match self.current().declared.get(&id) {
+                    Some(&body_id) => body_id,
+                    None => self.declare_fn(decl),
+                };
 
-                if is_generic {
+                if decl.is_generic() {
                     return RValue::UNIT;
                 }
 
-                self.bodies.push(BodyInfo::new(body_id));
-                if self.bodies.len() == 2 && ident == "main" {
+                if self.bodies.len() == 1 && decl.ident == "main" {
                     self.mir.main_body = Some(body_id);
                 }
-
-                if self.bodies.len() == 2 && self.try_intrinsic(for_ty, ident) {
-                    let current = self.current_mut().body;
-                    self.mir.bodies[current].auto = true;
-                } else {
-                    for (i, param) in params.iter().enumerate() {
-                        self.current_mut().scope().variables.insert(param.ident, Local::from(i));
-                    }
-                    let mut last = Operand::UNIT;
-                    for &expr in body {
-                        last = self.lower(expr);
-                    }
-                    self.finish_with(Terminator::Return(last));
+                self.lower_fn_body(decl, body_id);
+                RValue::UNIT
+            }
+            ExprKind::Closure { ref params, body } => {
+                let body_id = self.mir.bodies.push(Body::new(None, params.len()));
+                self.bodies.push(BodyInfo::new(body_id));
+                for (i, param) in params.iter().enumerate() {
+                    self.current_mut().scope().variables.insert(param.ident, Local::from(i));
                 }
+                let out = self.lower(body);
+                self.finish_with(Terminator::Return(out));
                 self.bodies.pop().unwrap();
-                RValue::UNIT
+                RValue::Use(Constant::Func(body_id).into())
             }
-            ExprKind::Let { ident, expr } => {
+            ExprKind::Let { ref binding, expr } => {
                 let rvalue = self.lower_rvalue(expr);
                 let local = self.assign_new(rvalue);
-                self.current_mut().scope().variables.insert(ident, local);
+                self.bind(binding, local, &mut vec![]);
                 RValue::UNIT
             }
             ExprKind::Return(expr) => {
                 let place = self.lower(expr);
                 self.finish_with(Terminator::Return(place));
-                RValue::UNIT
+                RValue::UNREACHABLE
             }
-            ExprKind::Loop(ref block) => {
+            ExprKind::Loop { body: ref block, label } => {
                 self.lower_loop(
+                    label,
                     |_| None,
                     |lower| {
                         for &expr in block {
@@ -341,7 +356,8 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
                         }
                     },
                 );
-                RValue::UNIT
+                // only a loop without a `break` is `!`
+                if self.ty(id).is_never() { RValue::UNREACHABLE } else { RValue::UNIT }
             }
             ExprKind::Match { scrutinee, ref arms } => self.lower_match(scrutinee, arms),
             ExprKind::If { ref arms, ref els } => {
@@ -379,9 +395,10 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
                 if is_unit { RValue::UNIT } else { RValue::local(out_local) }
             }
             ExprKind::Assignment { lhs, expr } => {
-                let rvalue = self.lower_rvalue(expr);
+                // like `op_assign`, the value is evaluated before any index in the place
+                let operand = self.lower(expr);
                 let place = self.lower_place(lhs);
-                self.assign(place, rvalue);
+                self.assign(place, operand);
                 RValue::UNIT
             }
             ExprKind::Binary { lhs, op, rhs } => self.binary_op(lhs, op, rhs),
@@ -389,40 +406,59 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
             ExprKind::OpAssign { place, op, expr } => self.op_assign(place, op, expr),
             ExprKind::Ident(ident) => self.load_ident(ident, self.ty(id)),
             ExprKind::Method { ty, method } => {
-                let location = self.methods[&(TyKey(ty), method)];
+                let location = self.methods.get(self.mono(ty), method).unwrap();
 
                 self.mono_fn(method, location, self.ty(id))
             }
-            ExprKind::FnCall { function, ref args } => {
+            ExprKind::FnCall { function, ref args, span, std } => {
+                let std = std.map(Symbol::as_str);
+                match std {
+                    Some("print") => return self.lower_print(UnaryOp::Print, args),
+                    Some("println") => return self.lower_print(UnaryOp::Println, args),
+                    Some("concat") => return self.lower_concat(args),
+                    _ => {}
+                }
                 let ty = match self.hir.exprs[function].kind {
                     ExprKind::Method { ty, .. } => Some(ty),
                     _ => None,
                 };
+                let is_map_get = std == Some("map_get");
                 let function = self.lower(function);
 
-                let args = args.iter().map(|arg| self.lower(*arg)).collect();
+                let args: ThinVec<_> = args.iter().map(|arg| self.lower(*arg)).collect();
+                if is_map_get {
+                    self.abort_missing_key(args[0].clone(), args[1].clone(), span);
+                }
 
                 match self.try_call_intrinsic(function, ty, args) {
                     Ok(rvalue) | Err(rvalue) => rvalue,
                 }
             }
-            ExprKind::Break => {
+            ExprKind::Break(None) => {
                 let block = self.finish_with(Terminator::Goto(BlockId::PLACEHOLDER));
                 self.current_mut().breaks.push(block);
-                RValue::UNIT
+                RValue::UNREACHABLE
             }
-            ExprKind::Continue => {
-                self.finish_with(Terminator::Goto(self.current().continue_block.unwrap()));
-                RValue::UNIT
+            ExprKind::Break(Some(label)) => {
+                let block = self.finish_with(Terminator::Goto(BlockId::PLACEHOLDER));
+                self.current_mut().label(label).breaks.push(block);
+                RValue::UNREACHABLE
+            }
+            ExprKind::Continue(label) => {
+                let continue_block = match label {
+                    Some(label) => self.current_mut().label(label).continue_block,
+                    None => self.current().continue_block.unwrap(),
+                };
+                self.finish_with(Terminator::Goto(continue_block));
+                RValue::UNREACHABLE
             }
             ExprKind::Index { expr, index, span } => {
                 let index_ty = self.ty(index);
                 let op = if self.ty(expr).is_str() {
-                    if index_ty.is_range() {
-                        mir::BinaryOp::StrIndexSlice
-                    } else {
-                        mir::BinaryOp::StrIndex
+                    if !index_ty.is_range() {
+                        return self.index_str(expr, index, span);
                     }
+                    mir::BinaryOp::StrIndexSlice
                 } else if index_ty.is_range() {
                     mir::BinaryOp::ArrayIndexRange
                 } else {
@@ -458,7 +494,7 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
 
     fn unary_op(&mut self, op: crate::ast::UnaryOp, expr: ExprId) -> RValue {
         match op {
-            hir::UnaryOp::Ref => RValue::Use(self.ref_expr(expr)),
+            hir::UnaryOp::Ref | hir::UnaryOp::RefMut => RValue::Use(self.ref_expr(expr)),
             hir::UnaryOp::Deref => {
                 let rvalue = self.lower_rvalue(expr);
                 RValue::Use(self.deref_operand(rvalue))
@@ -491,8 +527,13 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
         let (rhs, rhs_ty) = self.fully_deref(rhs, rhs_ty);
 
         let op = Self::get_binary_op(lhs_ty, op);
-        let lhs = self.process(lhs, lhs_ty);
-        let rhs = self.process(rhs, rhs_ty);
+        let mut lhs = self.process(lhs, lhs_ty);
+        let mut rhs = self.process(rhs, rhs_ty);
+        // char ranges are int ranges over codepoints
+        if lhs_ty.is_char() && matches!(op, BinaryOp::IntRange | BinaryOp::IntRangeInclusive) {
+            lhs = self.process(RValue::Unary { op: UnaryOp::Ord, operand: lhs }, Ty::INT);
+            rhs = self.process(RValue::Unary { op: UnaryOp::Ord, operand: rhs }, Ty::INT);
+        }
         RValue::Binary { lhs, op, rhs }
     }
 
@@ -514,17 +555,47 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
                 hir::BinaryOp::RangeInclusive => mir::BinaryOp::IntRangeInclusive,
                 _ => unreachable!(),
             },
+            (TyKind::Bool, op) => match op {
+                hir::BinaryOp::Eq => mir::BinaryOp::BoolEq,
+                hir::BinaryOp::Neq => mir::BinaryOp::BoolNeq,
+                _ => unreachable!("bool - {op:?}"),
+            },
             (TyKind::Char, op) => match op {
                 hir::BinaryOp::Eq => mir::BinaryOp::CharEq,
                 hir::BinaryOp::Neq => mir::BinaryOp::CharNeq,
+                hir::BinaryOp::Range => mir::BinaryOp::IntRange,
+                hir::BinaryOp::RangeInclusive => mir::BinaryOp::IntRangeInclusive,
                 _ => unreachable!("char - {op:?}"),
             },
             (TyKind::Str, op) => match op {
                 hir::BinaryOp::Eq => mir::BinaryOp::StrEq,
                 hir::BinaryOp::Neq => mir::BinaryOp::StrNeq,
+                hir::BinaryOp::Less => mir::BinaryOp::StrLess,
+                hir::BinaryOp::Greater => mir::BinaryOp::StrGreater,
+                hir::BinaryOp::LessEq => mir::BinaryOp::StrLessEq,
+                hir::BinaryOp::GreaterEq => mir::BinaryOp::StrGreaterEq,
                 hir::BinaryOp::Add => mir::BinaryOp::StrAdd,
                 _ => unreachable!("str - {op:?}"),
             },
+            (TyKind::Array(_), op) => match op {
+                hir::BinaryOp::Eq => mir::BinaryOp::ArrayEq,
+                hir::BinaryOp::Neq => mir::BinaryOp::ArrayNeq,
+                _ => unreachable!("array - {op:?}"),
+            },
+            (TyKind::Struct { .. } | TyKind::Tuple(_), op) => match op {
+                hir::BinaryOp::Eq => mir::BinaryOp::StructEq,
+                hir::BinaryOp::Neq => mir::BinaryOp::StructNeq,
+                hir::BinaryOp::Less => mir::BinaryOp::StructLess,
+                hir::BinaryOp::Greater => mir::BinaryOp::StructGreater,
+                hir::BinaryOp::LessEq => mir::BinaryOp::StructLessEq,
+                hir::BinaryOp::GreaterEq => mir::BinaryOp::StructGreaterEq,
+                _ => unreachable!("struct - {op:?}"),
+            },
+            (TyKind::Function(..), op) => match op {
+                hir::BinaryOp::Eq => mir::BinaryOp::FnEq,
+                hir::BinaryOp::Neq => mir::BinaryOp::FnNeq,
+                _ => unreachable!("fn - {op:?}"),
+            },
             (ty, op) => unreachable!("{ty:?} - {op:?}",),
         }
     }
@@ -576,7 +647,7 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
 
     fn fully_deref(&mut self, rvalue: impl Into<RValue>, mut ty: Ty<'tcx>) -> (RValue, Ty<'tcx>) {
         let mut rvalue = rvalue.into();
-        while let TyKind::Ref(of) = ty.0 {
+        while let TyKind::Ref(of, _) = ty.0 {
             rvalue = self.deref_operand(rvalue).into();
             ty = *of;
         }
@@ -585,14 +656,13 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
 
     fn index_array(&mut self, expr: ExprId, index: ExprId, span: Span) -> RValue {
         let expr_ty = self.ty(expr);
-        let index_ty = self.ty(index);
 
         let expr = self.lower_rvalue(expr);
         let (expr, _) = self.fully_deref(expr, expr_ty);
         let mut place = self.process_to_place(expr);
         let index_local = self.lower_local(index);
 
-        self.bounds_check((index_local, index_ty), (place.clone(), index_ty), span);
+        self.bounds_check(index_local, place.clone(), span);
 
         let projection = match self.hir.exprs[index].kind {
             ExprKind::Literal(Lit::Int(int)) if u32::try_from(int).is_ok() => {
@@ -604,38 +674,83 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
         RValue::Use(Operand::Place(place))
     }
 
-    fn bounds_check(
-        &mut self,
-        (index, index_ty): (Local, Ty<'tcx>),
-        (rhs, rhs_ty): (Place, Ty<'tcx>),
-        span: Span,
-    ) {
+    fn index_str(&mut self, expr: ExprId, index: ExprId, span: Span) -> RValue {
+        let lhs = self.lower(expr);
+        let index = self.lower_local(index);
+        let len = self.assign_new(RValue::Unary { op: UnaryOp::StrLen, operand: lhs.clone() });
+        self.abort_out_of_bounds(index, len, span, "string index out of bounds");
+        RValue::Binary { lhs, op: mir::BinaryOp::StrIndex, rhs: Operand::local(index) }
+    }
+
+    fn bounds_check(&mut self, index: Local, array: Place, span: Span) {
         let array_len =
-            self.assign_new(RValue::Unary { op: UnaryOp::ArrayLen, operand: Operand::Ref(rhs) });
-        let binary_op = self.binary_op_inner(
-            (RValue::local(index), index_ty),
+            self.assign_new(RValue::Unary { op: UnaryOp::ArrayLen, operand: Operand::Ref(array) });
+        self.abort_out_of_bounds(index, array_len, span, "index out of bounds");
+    }
+
+    // aborts unless `0 <= index < len`
+    fn abort_out_of_bounds(&mut self, index: Local, len: Local, span: Span, msg: &str) {
+        let too_large = self.binary_op_inner(
+            (RValue::local(index), Ty::INT),
             hir::BinaryOp::GreaterEq,
-            (RValue::local(array_len), rhs_ty),
+            (RValue::local(len), Ty::INT),
         );
-        let condition = self.process(binary_op, Ty::BOOL);
+        let condition = self.process(too_large, Ty::BOOL);
         let next = self.current_block() + 1;
-        let to_fix = self.finish_with(Terminator::Branch {
+        let too_large = self.finish_with(Terminator::Branch {
             condition,
-            fals: BlockId::PLACEHOLDER,
-            tru: next,
+            fals: next,
+            tru: BlockId::PLACEHOLDER,
         });
 
-        let error_report = errors::error(
-            "index out of bounds",
-            self.path,
-            self.src,
-            [(span, "index out of bounds")],
+        let negative = self.binary_op_inner(
+            (RValue::local(index), Ty::INT),
+            hir::BinaryOp::Less,
+            (Constant::Int(0).into(), Ty::INT),
         );
-        let error_str = format!("{error_report:?}").into();
-        self.finish_with(Terminator::Abort { msg: error_str });
+        let condition = self.process(negative, Ty::BOOL);
+        let negative = self.finish_with(Terminator::Branch {
+            condition,
+            fals: BlockId::PLACEHOLDER,
+            tru: BlockId::PLACEHOLDER,
+        });
+
+        let abort = self.abort(span, msg);
 
+        // both checks jump to the abort block, and passing both continues after it
         let current = self.current_block();
-        self.body_mut().blocks[to_fix].terminator.complete(current);
+        let blocks = &mut self.body_mut().blocks;
+        for check in [too_large, negative] {
+            if let Terminator::Branch { tru, .. } = &mut blocks[check].terminator {
+                *tru = abort;
+            }
+        }
+        blocks[negative].terminator.complete(current);
+    }
+
+    // aborts unless `map` contains `key`
+    fn abort_missing_key(&mut self, map: Operand, key: Operand, span: Span) {
+        let contains = RValue::Binary { lhs: map, op: mir::BinaryOp::MapContains, rhs: key };
+        let condition = self.process(contains, Ty::BOOL);
+        let check = self.finish_with(Terminator::Branch {
+            condition,
+            fals: BlockId::PLACEHOLDER,
+            tru: BlockId::PLACEHOLDER,
+        });
+        let abort = self.abort(span, "key not found");
+
+        let current = self.current_block();
+        let Terminator::Branch { fals, tru, .. } = &mut self.body_mut().blocks[check].terminator
+        else {
+            unreachable!()
+        };
+        (*fals, *tru) = (abort, current);
+    }
+
+    fn abort(&mut self, span: Span, msg: &str) -> BlockId {
+        let error_report = errors::error(msg, self.path, self.src, [(span, msg)]);
+        let error_str = format!("{error_report:?}").into();
+        self.finish_with(Terminator::Abort { msg: error_str })
     }
 
     fn read_ident(&self, ident: Symbol) -> Local {
@@ -664,16 +779,12 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
 
                 let local = self.lower_place_inner(expr, proj);
                 let mut expr_ty = self.ty(expr);
-                while let TyKind::Ref(of) = expr_ty.0 {
+                while let TyKind::Ref(of, _) = expr_ty.0 {
                     expr_ty = *of;
                     proj.push(Projection::Deref);
                 }
 
-                self.bounds_check(
-                    (index_local, self.ty(index)),
-                    (Place { local, projections: proj.clone() }, expr_ty),
-                    span,
-                );
+                self.bounds_check(index_local, Place { local, projections: proj.clone() }, span);
 
                 let projection = match const_index {
                     Some(index) => Projection::ConstantIndex(index),
@@ -687,7 +798,7 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
                 proj.push(Projection::Deref);
                 local
             }
-            ExprKind::Unary { op: hir::UnaryOp::Ref, expr } => {
+            ExprKind::Unary { op: hir::UnaryOp::Ref | hir::UnaryOp::RefMut, expr } => {
                 let rvalue = self.ref_expr(expr);
                 self.process_to_local(rvalue)
             }
@@ -697,6 +808,9 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
                 proj.push(Projection::Field(field));
                 local
             }
+            // temporaries are moved into a fresh local, so `&(1 + 2)` refers to a new binding.
+            // the interpreter's allocations are reference counted, so it lives as long as any
+            // reference to it, even past the end of the function.
             _ => {
                 let expr = self.lower_rvalue(expr);
                 self.process_to_local(expr)
@@ -714,18 +828,68 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
         }
     }
 
+    fn declare_fn(&mut self, decl: &'hir hir::FnDecl<'tcx>) -> BodyId {
+        let hir::FnDecl { ident, for_ty, ref params, .. } = *decl;
+        let is_generic = decl.is_generic();
+
+        let body_id =
+            self.mir.bodies.push(Body::new(Some(ident), params.len()).with_auto(is_generic));
+
+        if is_generic {
+            self.generic_fns.insert(body_id, GenericFns { decl, impls: HashMap::default() });
+        }
+
+        match for_ty {
+            Some(ty) => self.methods.insert(ty, ident, body_id),
+            None => _ = self.current_mut().functions.insert(ident, body_id),
+        }
+        body_id
+    }
+
+    // registers every function declared directly in `exprs` (or in an impl block among them)
+    // up front, so that calls may appear before the declaration.
+    fn declare_items(&mut self, exprs: &[ExprId]) {
+        for &id in exprs {
+            if self.current().declared.contains_key(&id) {
+                continue;
+            }
+            match self.hir.exprs[id].kind {
+                ExprKind::FnDecl(ref decl) => {
+                    let body_id = self.declare_fn(decl);
+                    self.current_mut().declared.insert(id, body_id);
+                }
+                ExprKind::Block(ref items) => self.declare_items(items),
+                _ => {}
+            }
+        }
+    }
+
     fn block_expr(&mut self, exprs: &[ExprId]) -> RValue {
         self.begin_scope();
-        let mut rvalue = None;
+        self.declare_items(exprs);
+        let rvalue = self.lower_stmts(exprs);
+        self.end_scope();
+        rvalue
+    }
+
+    // lowers `exprs` in order, returning the value of the last one. statements after one that
+    // diverges are never reached and are skipped, only items among them are still lowered.
+    fn lower_stmts(&mut self, exprs: &[ExprId]) -> RValue {
+        let mut out = RValue::UNIT;
         for (i, &expr) in exprs.iter().enumerate() {
-            if i == exprs.len() - 1 {
-                rvalue = Some(self.lower_rvalue(expr));
-            } else {
-                self.lower(expr);
+            if out.is_unreachable() {
+                if matches!(self.hir.exprs[expr].kind, ExprKind::FnDecl(..)) {
+                    self.lower(expr);
+                }
+                continue;
             }
+            out = if i == exprs.len() - 1 {
+                self.lower_rvalue(expr)
+            } else {
+                self.lower(expr).into()
+            };
         }
-        self.end_scope();
-        rvalue.unwrap_or(RValue::UNIT)
+        out
     }
 
     fn load_ident(&mut self, ident: Symbol, ty: Ty<'tcx>) -> RValue {
@@ -748,6 +912,7 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
             Lit::Char(char) => RValue::from(Constant::Char(char)),
             Lit::String(str) => str!(self, str),
             Lit::Array { ref segments } => self.lower_array_lit(segments),
+            Lit::Tuple(ref elems) => self.lower_tuple_lit(elems),
             Lit::FStr { ref segments } => self.lower_fstrings(segments),
         }
     }
@@ -766,6 +931,38 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
         RValue::BuildArray(mir_segments)
     }
 
+    // every name in a destructuring `let` gets its own local, copied out of the tuple's fields
+    fn bind(&mut self, binding: &hir::Binding, local: Local, projections: &mut Vec<Projection>) {
+        match binding {
+            hir::Binding::Ident(ident) if projections.is_empty() => {
+                self.current_mut().scope().variables.insert(ident.symbol, local);
+            }
+            hir::Binding::Ident(ident) => {
+                let place = Place { local, projections: projections.clone() };
+                let elem = self.assign_new(RValue::Use(Operand::Place(place)));
+                self.current_mut().scope().variables.insert(ident.symbol, elem);
+            }
+            hir::Binding::Tuple(elems) => {
+                for (i, elem) in (0..).zip(elems) {
+                    projections.push(Projection::Field(i));
+                    self.bind(elem, local, projections);
+                    projections.pop();
+                }
+            }
+        }
+    }
+
+    // tuples share their representation with structs
+    fn lower_tuple_lit(&mut self, elems: &[ExprId]) -> RValue {
+        let elems: Vec<_> = elems.iter().map(|&elem| self.lower(elem)).collect();
+        let local =
+            self.assign_new(Constant::UninitStruct { size: elems.len().try_into().unwrap() });
+        for (i, elem) in (0..).zip(elems) {
+            self.assign(Place { local, projections: vec![Projection::Field(i)] }, elem);
+        }
+        RValue::local(local)
+    }
+
     fn lower_fstrings(&mut self, segments: &[ExprId]) -> RValue {
         if let [single] = *segments {
             return self.format_expr(single);
@@ -794,22 +991,27 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
         let operand = self.process(rvalue, ty);
 
         match ty.0 {
-            TyKind::Poison
-            | TyKind::Generic(_)
-            | TyKind::Ref(_)
-            | TyKind::Infer(_)
-            | TyKind::Str => {
+            TyKind::Poison | TyKind::Generic(_) | TyKind::Ref(..) | TyKind::Str => {
                 unreachable!("{ty:?}");
             }
+            TyKind::Infer(_) => {
+                unreachable!("internal compiler error: uninferred type `{ty:?}` reached lowering");
+            }
             TyKind::Never => str!("!"),
             TyKind::Unit => str!("()"),
             TyKind::Bool => RValue::Unary { op: UnaryOp::BoolToStr, operand },
             TyKind::Int => RValue::Unary { op: UnaryOp::IntToStr, operand },
             TyKind::Char => RValue::Unary { op: UnaryOp::CharToStr, operand },
             TyKind::Range => RValue::Unary { op: UnaryOp::RangeToStr, operand },
-            TyKind::Struct { id, fields, .. } => self.format_struct(*id, fields, operand),
+            TyKind::CharRange => RValue::Unary { op: UnaryOp::CharRangeToStr, operand },
+            TyKind::Struct { .. } => {
+                let fields = self.tcx.struct_fields(ty);
+                self.format_struct(ty, &fields, operand)
+            }
+            TyKind::Tuple(fields) => self.format_struct(ty, fields, operand),
             TyKind::Array(of) => self.format_array(*of, operand),
-            TyKind::Function(..) => {
+            TyKind::Function(..) => RValue::Unary { op: UnaryOp::FnToStr, operand },
+            TyKind::Map(..) | TyKind::Set(..) => {
                 RValue::from(Constant::Str(self.tcx.display(ty).to_string().into()))
             }
         }
@@ -835,8 +1037,8 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
         RValue::Call { function: Constant::Func(body).into(), args: [ref_array].into() }
     }
 
-    fn format_struct(&mut self, id: StructId, fields: &[Ty<'tcx>], val: Operand) -> RValue {
-        let body = self.generate_struct_func(id, fields);
+    fn format_struct(&mut self, ty: Ty<'tcx>, fields: &[Ty<'tcx>], val: Operand) -> RValue {
+        let body = self.generate_struct_func(ty, fields);
         let ref_struct = self.ref_of(val);
         RValue::Call {
             function: Operand::Constant(Constant::Func(body)),
@@ -872,6 +1074,7 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
         let index = self.assign_new(Constant::Int(0));
 
         self.lower_loop(
+            None,
             |lower| {
                 Some(lower.assign_new(RValue::Binary {
                     lhs: Operand::local(index),
@@ -913,30 +1116,47 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
         Operand::local(out)
     }
 
-    fn generate_struct_func(&mut self, id: StructId, fields: &[Ty<'tcx>]) -> BodyId {
-        if let Some(Some(body)) = self.struct_display_bodies.get(id) {
+    // generic structs get a separate body for every instantiation
+    fn generate_struct_func(&mut self, ty: Ty<'tcx>, fields: &[Ty<'tcx>]) -> BodyId {
+        if let Some(body) = self.struct_display_bodies.get(&ty) {
             return *body;
         }
         let previous = mem::take(&mut self.bodies);
-        let body_id = self.mir.bodies.push(Body::new(None, 1).with_auto(true));
+        let body_id =
+            self.mir.bodies.push(Body::new(Some("format_struct".into()), 1).with_auto(true));
         self.bodies.push(BodyInfo::new(body_id));
 
-        if self.struct_display_bodies.len() <= id {
-            self.struct_display_bodies.resize(id.index() + 1, None);
-        }
-        self.struct_display_bodies[id] = Some(body_id);
+        self.struct_display_bodies.insert(ty, body_id);
 
-        let mut segments = vec![str!("(")];
+        // structs show their name and fields like `Point { x: 1, y: 2 }`, tuples just the values.
+        // `text` collects the literal text up to the next field
+        let (mut text, names, close) = match ty.0 {
+            TyKind::Struct { id, .. } => {
+                let symbols = self.tcx.struct_symbols(*id);
+                if symbols.is_empty() {
+                    (self.tcx.struct_name(*id).to_string(), None, "")
+                } else {
+                    (format!("{} {{ ", self.tcx.struct_name(*id)), Some(symbols), " }")
+                }
+            }
+            _ => ("(".to_string(), None, ")"),
+        };
+        let mut segments = vec![];
         for (i, ty) in (0u32..).zip(fields) {
             if i != 0 {
-                segments.push(str!(", "));
+                text.push_str(", ");
             }
+            if let Some(names) = &names {
+                text = format!("{text}{}: ", names[i as usize]);
+            }
+            segments.push(Constant::Str(mem::take(&mut text).into()).into());
             let projections = vec![Projection::Deref, Projection::Field(i as _)];
             let field = Operand::Place(Place { local: Local::from(0), projections });
             let field_str = self.format_rvalue(field, *ty);
             segments.push(Operand::local(self.assign_new(field_str)));
         }
-        segments.push(str!(")"));
+        text.push_str(close);
+        segments.push(Constant::Str(text.into()).into());
 
         let segments = segments.into_iter().map(|operand| (operand, None)).collect();
         let strings = self.assign_new(RValue::BuildArray(segments));
@@ -953,25 +1173,28 @@ impl<'tcx> Lowering<'_, 'tcx, '_> {
         while let Some(new_impl) = self.mono_generics.pop_front() {
             let (decl, fn_ty, body_id) = new_impl;
             self.generic_map = Some(produce_generic_map(decl, fn_ty));
-            let hir::FnDecl { ident, for_ty, ref body, ref params, .. } = *decl;
+            self.lower_fn_body(decl, body_id);
+        }
+    }
 
-            self.bodies.push(BodyInfo::new(body_id));
+    // shared by fns lowered where they are declared and generic fns lowered once per instance
+    fn lower_fn_body(&mut self, decl: &hir::FnDecl<'tcx>, body_id: BodyId) {
+        let hir::FnDecl { ident, for_ty, ref body, ref params, .. } = *decl;
+        self.bodies.push(BodyInfo::new(body_id));
 
-            if self.bodies.len() == 2 && self.try_intrinsic(for_ty, ident) {
-                let current = self.current_mut().body;
-                self.mir.bodies[current].auto = true;
-            } else {
-                for (i, param) in params.iter().enumerate() {
-                    self.current_mut().scope().variables.insert(param.ident, Local::from(i));
-                }
-                let mut last = Operand::UNIT;
-                for &expr in body {
-                    last = self.lower(expr);
-                }
-                self.finish_with(Terminator::Return(last));
+        if self.bodies.len() == 2 && self.try_intrinsic(for_ty, ident) {
+            let current = self.current_mut().body;
+            self.mir.bodies[current].auto = true;
+        } else {
+            for (i, param) in params.iter().enumerate() {
+                self.current_mut().scope().variables.insert(param.ident, Local::from(i));
             }
-            self.bodies.pop().unwrap();
+            self.declare_items(body);
+            let last = self.lower_stmts(body);
+            let last = self.process(last, body.last().map_or(Ty::UNIT, |&e| self.ty(e)));
+            self.finish_with(Terminator::Return(last));
         }
+        self.bodies.pop().unwrap();
     }
 }
 
@@ -1007,13 +1230,19 @@ fn generic_map_ty<'tcx>(
             generic.params.iter().zip(&mono.params).for_each(|(&g, &m)| generic_map_ty(g, m, into));
             generic_map_ty(generic.ret, mono.ret, into);
         }
-        (TyKind::Struct { fields: lfields, .. }, TyKind::Struct { fields: rfields, .. }) => {
+        (TyKind::Struct { generics: lfields, .. }, TyKind::Struct { generics: rfields, .. })
+        | (TyKind::Tuple(lfields), TyKind::Tuple(rfields)) => {
             for (generic, mono) in lfields.iter().zip(rfields) {
                 generic_map_ty(*generic, *mono, into);
             }
         }
         (TyKind::Array(generic), TyKind::Array(mono))
-        | (TyKind::Ref(generic), TyKind::Ref(mono)) => generic_map_ty(*generic, *mono, into),
+        | (TyKind::Set(generic), TyKind::Set(mono))
+        | (TyKind::Ref(generic, _), TyKind::Ref(mono, _)) => generic_map_ty(*generic, *mono, into),
+        (TyKind::Map(generic_key, generic_value), TyKind::Map(mono_key, mono_value)) => {
+            generic_map_ty(*generic_key, *mono_key, into);
+            generic_map_ty(*generic_value, *mono_value, into);
+        }
         _ => {}
     }
 }
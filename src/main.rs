@@ -21,7 +21,8 @@ mod ty;
 
 pub use cli::Args;
 pub use codegen_opts::CodegenOpts;
-use rustc_hash::FxHashMap as HashMap;
+// fx hashing has no random seed, so iteration order is the same between runs and machines
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
 use source::span;
 
 const STD: &str = concat!(include_str!("std.pty"), "\n\n");
@@ -29,18 +30,33 @@ const STD: &str = concat!(include_str!("std.pty"), "\n\n");
 fn main() {
     let args = Args::parse();
     let mut stdout = std::io::stdout().lock();
-    match compile::compile(&args, &mut stdout) {
-        Ok(()) => {
+    let result = compile::compile(&args, &mut stdout);
+    // `exit` skips destructors, so release the lock for stdout to be flushed
+    drop(stdout);
+    let result = result.map(|compiled| {
+        for warning in compiled.warnings {
+            eprintln!("{warning:?}");
+        }
+        compiled.code
+    });
+    match result {
+        Ok(Ok(code)) => {
             if let Some(target) = args.dump {
                 if args.verbose > 0 {
                     log!("mir dumped to {}/dump-mir.txt", target.display());
                 }
             }
+            std::process::exit(code);
+        }
+        Ok(Err(err)) => {
+            eprintln!("{err}");
+            std::process::exit(1);
         }
         Err(errors) => {
             for err in errors {
                 eprintln!("{err:?}");
             }
+            std::process::exit(1);
         }
     }
 }
@@ -1,6 +1,6 @@
 use std::fmt::{self};
 
-use super::{Constant, Mir, Operand, Place, Projection, RValue, Statement, Terminator};
+use super::{Constant, IntRange, Mir, Operand, Place, Projection, RValue, Statement, Terminator};
 
 impl Mir {
     pub fn display(&self, show_auto: bool) -> impl fmt::Display {
@@ -21,6 +21,9 @@ impl Mir {
             if !show_auto && body.auto {
                 continue;
             }
+            if body.auto {
+                write!(f, "auto ")?;
+            }
             write!(f, "fn ")?;
             match body.name {
                 Some(name) => write!(f, "{id:?}({name})"),
@@ -133,6 +136,7 @@ impl fmt::Display for OperandDisplay<'_, '_> {
             Operand::Ref(place) => write!(f, "&{place}"),
             Operand::Place(place) => write!(f, "{place}"),
             Operand::Constant(constant) => write!(f, "{}", constant.display(self.0)),
+            Operand::Unreachable => write!(f, "unreachable"),
         }
     }
 }
@@ -166,10 +170,13 @@ impl fmt::Display for ConstDisplay<'_, '_> {
         match self.1 {
             Constant::UninitStruct { size } => write!(f, "struct {{ {size:?} }}"),
             Constant::EmptyArray { cap } => write!(f, "[cap: {cap}]"),
+            Constant::EmptyMap => write!(f, "Map {{}}"),
+            Constant::EmptySet => write!(f, "Set {{}}"),
             Constant::Unit => write!(f, "()"),
             Constant::Bool(bool) => write!(f, "{bool}"),
             Constant::Int(int) => write!(f, "{int}"),
-            Constant::Range(range) => write!(f, "{range:?}"),
+            Constant::Byte(byte) => write!(f, "{byte}u8"),
+            Constant::Range(range) => write!(f, "{range}"),
             Constant::Char(char) => write!(f, "{char:?}"),
             Constant::Str(str) => write!(f, "{str:?}"),
             Constant::Func(id) => match self.0.bodies[*id].name {
@@ -184,3 +191,9 @@ impl Constant {
         ConstDisplay(mir, self)
     }
 }
+
+impl fmt::Display for IntRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.start, self.separator(), self.displayed_end())
+    }
+}
@@ -1,8 +1,6 @@
 mod display;
 mod with_places;
 
-use std::ops::Range;
-
 use arcstr::ArcStr;
 use index_vec::IndexVec;
 use thin_vec::ThinVec;
@@ -23,6 +21,17 @@ impl Place {
     pub fn local(local: Local) -> Self {
         Self { local, projections: vec![] }
     }
+
+    // whether both places could access the same memory, i.e. one is a prefix of the other.
+    // dynamic indices are assumed to alias.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.local == other.local
+            && self.projections.iter().zip(&other.projections).all(|pair| match pair {
+                (Projection::Field(a), Projection::Field(b))
+                | (Projection::ConstantIndex(a), Projection::ConstantIndex(b)) => a == b,
+                _ => true,
+            })
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -54,6 +63,7 @@ pub struct Body {
     pub name: Option<Symbol>,
     pub auto: bool,
     pub blocks: IndexVec<BlockId, Block>,
+    // the first `params` locals hold the arguments and are always considered live
     pub params: usize,
     pub locals: Local,
 }
@@ -155,11 +165,13 @@ pub enum RValue {
 
 impl RValue {
     pub const UNIT: Self = Self::Use(Operand::UNIT);
+    pub const UNREACHABLE: Self = Self::Use(Operand::Unreachable);
 
     pub fn local(local: Local) -> Self {
         Self::Use(Operand::local(local))
     }
 
+    // whether this rvalue must be evaluated even if its result is never read
     pub fn side_effect(&self) -> bool {
         match self {
             Self::StrJoin(..) | Self::BuildArray(..) | Self::Use(..) => false,
@@ -168,6 +180,10 @@ impl RValue {
             Self::Call { .. } => true,
         }
     }
+
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self, Self::Use(Operand::Unreachable))
+    }
 }
 
 #[derive(Debug, Clone, Hash)]
@@ -175,6 +191,8 @@ pub enum Operand {
     Constant(Constant),
     Ref(Place),
     Place(Place),
+    // the result of an expression that diverges, code reading it is never reached
+    Unreachable,
 }
 
 impl Operand {
@@ -190,20 +208,43 @@ impl Operand {
     }
 }
 
+// stored half-open, `inclusive` only remembers how the range was written for display
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct IntRange {
+    pub start: i64,
+    pub end: i64,
+    pub inclusive: bool,
+}
+
+impl IntRange {
+    // the last element of an inclusive range, or the exclusive end
+    pub fn displayed_end(self) -> i64 {
+        if self.inclusive { self.end - 1 } else { self.end }
+    }
+    pub fn separator(self) -> &'static str {
+        if self.inclusive { "..=" } else { ".." }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub enum Constant {
     Unit,
     EmptyArray { cap: usize },
+    EmptyMap,
+    EmptySet,
     UninitStruct { size: u32 },
     Bool(bool),
     Int(i64),
-    Range(Range<i64>),
+    Byte(u8),
+    Range(IntRange),
     Char(char),
     Str(ArcStr),
     Func(BodyId),
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Eq, Hash, Clone, Copy)]
+// nothing lowers to the byte ops until `u8` exists
+#[cfg_attr(not(test), expect(dead_code))]
 pub enum BinaryOp {
     IntAdd,
     IntSub,
@@ -219,11 +260,24 @@ pub enum BinaryOp {
     IntRange,
     IntRangeInclusive,
 
+    // bytes wrap around on overflow
+    ByteAdd,
+    ByteSub,
+    ByteEq,
+    ByteNeq,
+
+    BoolEq,
+    BoolNeq,
+
     CharEq,
     CharNeq,
 
     StrEq,
     StrNeq,
+    StrLess,
+    StrGreater,
+    StrLessEq,
+    StrGreaterEq,
     StrAdd,
     StrFind,
     StrRFind,
@@ -232,11 +286,31 @@ pub enum BinaryOp {
 
     ArrayIndexRange,
     ArrayPush,
+    ArrayContains,
+    ArrayEq,
+    ArrayNeq,
+
+    StructEq,
+    StructNeq,
+    StructLess,
+    StructGreater,
+    StructLessEq,
+    StructGreaterEq,
+
+    FnEq,
+    FnNeq,
+
+    MapEntry,
+    MapGet,
+    MapContains,
+
+    SetAdd,
+    SetContains,
 }
 
 impl BinaryOp {
     pub fn side_effect(self) -> bool {
-        matches!(self, Self::ArrayPush)
+        matches!(self, Self::ArrayPush | Self::MapEntry | Self::SetAdd)
     }
 }
 
@@ -246,6 +320,8 @@ pub enum UnaryOp {
     BoolToStr,
 
     IntToStr,
+    IntToHex,
+    IntToBin,
     IntNeg,
     Chr,
 
@@ -253,22 +329,34 @@ pub enum UnaryOp {
     CharToStr,
 
     StrLen,
+    StrCharLen,
     Print,
+    Println,
 
     ArrayLen,
     StrJoin,
 
     RangeToStr,
+    CharRangeToStr,
     RangeStart,
     RangeEnd,
 
+    FnToStr,
+
     ArrayPop,
+    ArraySort,
+    ArrayReverse,
     ArrayStrFmt,
+
+    SetLen,
 }
 
 impl UnaryOp {
     pub fn side_effect(self) -> bool {
-        matches!(self, Self::Print | Self::ArrayPop)
+        matches!(
+            self,
+            Self::Print | Self::Println | Self::ArrayPop | Self::ArraySort | Self::ArrayReverse
+        )
     }
 }
 
@@ -342,10 +430,9 @@ impl RValue {
 
 impl Operand {
     pub fn mentions_place(&self, target: &Place) -> bool {
-        // FIXME: This seems iffy.
         match self {
-            Self::Ref(place) | Self::Place(place) => target.local == place.local,
-            Self::Constant(..) => false,
+            Self::Ref(place) | Self::Place(place) => place.overlaps(target),
+            Self::Constant(..) | Self::Unreachable => false,
         }
     }
     pub fn mutates_local(&self, local: Local) -> bool {
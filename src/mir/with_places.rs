@@ -78,13 +78,13 @@ impl Operand {
     pub fn with_locals(&self, f: impl FnMut(Local)) {
         match self {
             Self::Place(place) | Self::Ref(place) => place.with_locals(f),
-            Self::Constant(..) => {}
+            Self::Constant(..) | Self::Unreachable => {}
         }
     }
     pub fn with_locals_mut(&mut self, f: impl FnMut(&mut Local)) {
         match self {
             Self::Place(place) | Self::Ref(place) => place.with_locals_mut(f),
-            Self::Constant(..) => {}
+            Self::Constant(..) | Self::Unreachable => {}
         }
     }
 }
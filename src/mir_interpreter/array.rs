@@ -32,8 +32,8 @@ impl Array {
     pub fn push(&self, value: Value) {
         self.with(|vec| vec.push(value.into()));
     }
-    pub fn pop(&self) -> Value {
-        self.with(|vec| vec.pop().unwrap().clone_raw())
+    pub fn pop(&self) -> Option<Value> {
+        self.with(|vec| vec.pop().map(|alloc| alloc.clone_raw()))
     }
     pub fn len(&self) -> usize {
         self.with(|vec| vec.len())
@@ -41,6 +41,27 @@ impl Array {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+    pub fn sort(&self) {
+        self.with(|vec| vec.sort_by(Allocation::structural_cmp));
+    }
+    pub fn reverse(&self) {
+        self.with(|vec| vec.reverse());
+    }
+    pub fn contains(&self, value: &Value) -> bool {
+        self.with(|vec| vec.iter().any(|elem| elem.eq_value(value)))
+    }
+    pub fn structural_eq(&self, other: &Self) -> bool {
+        // both sides share the same storage, which can't be taken twice
+        if Rc::ptr_eq(&self.inner, &other.inner) {
+            return true;
+        }
+        self.with(|lhs| {
+            other.with(|rhs| {
+                lhs.len() == rhs.len()
+                    && lhs.iter().zip(rhs.iter()).all(|(l, r)| l.structural_eq(r))
+            })
+        })
+    }
     pub fn for_each(&self, f: impl FnMut(&mut Allocation)) {
         self.with(|vec| vec.iter_mut().for_each(f));
     }
@@ -0,0 +1,28 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use super::{Allocation, Value};
+use crate::HashMap;
+
+#[derive(Clone, Default)]
+pub struct Map {
+    inner: Rc<RefCell<HashMap<Value, Allocation>>>,
+}
+
+impl Map {
+    // returns the allocation stored for `key`, inserting `()` if it's missing
+    pub fn entry(&self, key: Value) -> Allocation {
+        self.inner.borrow_mut().entry(key).or_insert_with(|| Value::Unit.into()).clone()
+    }
+    pub fn get(&self, key: &Value) -> Option<Value> {
+        self.inner.borrow().get(key).map(Allocation::clone_raw)
+    }
+    pub fn contains(&self, key: &Value) -> bool {
+        self.inner.borrow().contains_key(key)
+    }
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.borrow().fmt(f)
+    }
+}
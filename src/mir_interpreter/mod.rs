@@ -1,34 +1,154 @@
+// a value of the wrong type is a bug in lowering, so point at the mir that caused it
+macro_rules! internal_error {
+    ($($arg:tt)*) => {
+        unreachable!("{}{}", format_args!($($arg)*), $crate::mir_interpreter::location_suffix())
+    };
+}
+
 mod array;
+mod map;
+mod set;
 mod value;
 
-use std::{io::Write, ops::Range};
+use std::{cell::Cell, fmt, io::Write};
 
 use arcstr::ArcStr;
 use array::Array;
 use index_vec::{IndexSlice, IndexVec};
-use value::Allocation;
-pub use value::Value;
+use map::Map;
+use set::Set;
+pub use value::{Allocation, Value};
 
-use crate::mir::{
-    BinaryOp, BlockId, BodyId, Constant, Local, Mir, Operand, Place, Projection, RValue, Statement,
-    Terminator, UnaryOp,
+use crate::{
+    mir::{
+        BinaryOp, BlockId, BodyId, Constant, IntRange, Local, Mir, Operand, Place, Projection,
+        RValue, Statement, Terminator, UnaryOp,
+    },
+    symbol::Symbol,
 };
 
 type Places = IndexSlice<Local, [Allocation]>;
 
-pub fn interpret(mir: &Mir, w: &mut dyn Write) {
-    let Some(main) = mir.main_body else { return };
-    let mut interpreter = Interpreter { mir, allocs: vec![], w };
-    interpreter.run(main, vec![]);
+/// An error caused by the program being run, rather than by a bug in the compiler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeError {
+    // the message is already rendered, such as a failed assertion or bounds check
+    Abort(Symbol),
+    DivideByZero,
+    Overflow,
+    IndexOutOfBounds { index: i64, len: usize },
+    SliceOutOfBounds { start: i64, end: i64, len: usize },
+    PopEmptyArray,
+    KeyNotFound,
+    SubstringNotFound,
+    InvalidChar(i64),
+    StepLimitExceeded,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Abort(msg) => write!(f, "{msg}"),
+            Self::DivideByZero => write!(f, "attempt to divide by zero"),
+            Self::Overflow => write!(f, "integer overflow"),
+            Self::IndexOutOfBounds { index, len } => {
+                write!(f, "index out of bounds: the len is {len} but the index is {index}")
+            }
+            Self::SliceOutOfBounds { start, end, len } => {
+                write!(f, "slice out of bounds: the len is {len} but the range is {start}..{end}")
+            }
+            Self::PopEmptyArray => write!(f, "cannot pop from an empty array"),
+            Self::KeyNotFound => write!(f, "key not found"),
+            Self::SubstringNotFound => write!(f, "substring not found"),
+            Self::InvalidChar(code) => write!(f, "{code} is not a valid char"),
+            Self::StepLimitExceeded => write!(f, "step limit exceeded"),
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+// returns the exit code, which is whatever `main` returns if it returns an int.
+pub fn interpret(mir: &Mir, w: &mut dyn Write) -> Result<i32> {
+    interpret_limited(mir, w, u64::MAX)
+}
+
+// like `interpret`, but fails once more than `max_steps` statements and terminators have run
+pub fn interpret_limited(mir: &Mir, w: &mut dyn Write, max_steps: u64) -> Result<i32> {
+    interpret_with(mir, w, max_steps, None)
+}
+
+// like `interpret`, but calls `trace` before executing each statement and terminator
+#[cfg_attr(not(test), expect(dead_code))]
+pub fn interpret_traced(mir: &Mir, w: &mut dyn Write, trace: &mut TraceHook) -> Result<i32> {
+    interpret_with(mir, w, u64::MAX, Some(trace))
+}
+
+/// Something the interpreter is about to execute, as seen by a [`TraceHook`].
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(test), expect(dead_code))]
+pub enum Step<'mir> {
+    Statement(&'mir Statement),
+    Terminator(&'mir Terminator),
 }
 
-struct Interpreter<'mir, 'w> {
+pub type TraceHook<'a> = dyn FnMut(BodyId, BlockId, Step<'_>) + 'a;
+
+#[expect(clippy::cast_possible_truncation)]
+fn interpret_with(
+    mir: &Mir,
+    w: &mut dyn Write,
+    max_steps: u64,
+    trace: Option<&mut TraceHook<'_>>,
+) -> Result<i32> {
+    let Some(main) = mir.main_body else { return Ok(0) };
+    let mut interpreter = Interpreter { mir, allocs: vec![], w, steps_left: max_steps, trace };
+    let output = interpreter.run(main, vec![]);
+    // values are also computed outside of the interpreter, such as while folding constants
+    LOCATION.set(None);
+    match output? {
+        Value::Int(code) => Ok(code as i32),
+        _ => Ok(0),
+    }
+}
+
+thread_local! {
+    // the location being executed, reported by internal errors
+    static LOCATION: Cell<Option<Location>> = const { Cell::new(None) };
+}
+
+fn location_suffix() -> String {
+    LOCATION.get().map_or_else(String::new, |location| format!("\n  at {location}"))
+}
+
+struct Interpreter<'mir, 'w, 'h, 't> {
     mir: &'mir Mir,
     allocs: Vec<Allocation>,
     w: &'w mut dyn Write,
+    steps_left: u64,
+    trace: Option<&'h mut TraceHook<'t>>,
 }
 
-impl Interpreter<'_, '_> {
+// the statement being executed, or the terminator if `statement` is `None`
+#[derive(Debug, Clone, Copy)]
+struct Location {
+    body: BodyId,
+    block: BlockId,
+    statement: Option<usize>,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { body, block, statement } = self;
+        write!(f, "fn {body:?}, block {block:?}, ")?;
+        match statement {
+            Some(statement) => write!(f, "statement {statement}"),
+            None => write!(f, "terminator"),
+        }
+    }
+}
+
+impl Interpreter<'_, '_, '_, '_> {
     pub fn alloc_locals(&mut self, size: usize) -> IndexVec<Local, Allocation> {
         std::iter::repeat_with(|| {
             self.allocs.pop().unwrap_or_else(|| Allocation::from(Value::Unit))
@@ -45,7 +165,15 @@ impl Interpreter<'_, '_> {
         }
     }
 
-    fn run(&mut self, body_id: BodyId, args: Vec<Value>) -> Value {
+    fn step(&mut self, body: BodyId, block: BlockId, step: Step) -> Result<()> {
+        self.steps_left = self.steps_left.checked_sub(1).ok_or(RuntimeError::StepLimitExceeded)?;
+        if let Some(trace) = &mut self.trace {
+            trace(body, block, step);
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, body_id: BodyId, args: Vec<Value>) -> Result<Value> {
         let body = &self.mir.bodies[body_id];
         let mut block_id = BlockId::from(0);
         let locals = self.alloc_locals(body.locals.index());
@@ -54,93 +182,113 @@ impl Interpreter<'_, '_> {
         }
         let output = loop {
             let block = &body.blocks[block_id];
-            for stmt in &block.statements {
+            for (i, stmt) in block.statements.iter().enumerate() {
                 let Statement::Assign { place, rvalue } = stmt;
-                let rvalue = self.rvalue(rvalue, &locals);
-                let alloc = self.load_place(place, &locals);
+                let location = Location { body: body_id, block: block_id, statement: Some(i) };
+                LOCATION.set(Some(location));
+                self.step(body_id, block_id, Step::Statement(stmt))?;
+                // the rvalue is evaluated before the place is borrowed, so an assignment
+                // such as `arr[0] = arr[0] + 1` never holds two borrows of one allocation
+                let rvalue = self.rvalue(rvalue, &locals)?;
+                // a call moves the location into the callee
+                LOCATION.set(Some(location));
+                let alloc = self.load_place(place, &locals)?;
                 *alloc.borrow() = rvalue;
             }
+            LOCATION.set(Some(Location { body: body_id, block: block_id, statement: None }));
+            self.step(body_id, block_id, Step::Terminator(&block.terminator))?;
             match block.terminator {
-                Terminator::Unreachable => unreachable!(),
-                Terminator::Abort { msg } => panic!("{}", msg),
+                Terminator::Unreachable => internal_error!("reached an `unreachable` expression"),
+                Terminator::Abort { msg } => return Err(RuntimeError::Abort(msg)),
                 Terminator::Goto(block) => block_id = block,
                 Terminator::Branch { ref condition, fals, tru } => {
-                    let condition = self.operand(condition, &locals).unwrap_bool();
+                    let condition = self.operand(condition, &locals)?.unwrap_bool();
                     block_id = if condition { tru } else { fals };
                 }
-                Terminator::Return(ref operand) => break self.operand(operand, &locals),
+                Terminator::Return(ref operand) => break self.operand(operand, &locals)?,
             }
         };
         self.dealloc_locals(locals);
-        output
+        Ok(output)
     }
     #[allow(clippy::too_many_lines)]
-    fn rvalue(&mut self, rvalue: &RValue, locals: &Places) -> Value {
-        match rvalue {
+    fn rvalue(&mut self, rvalue: &RValue, locals: &Places) -> Result<Value> {
+        Ok(match rvalue {
             RValue::StrJoin(segments) => {
                 let mut s = String::new();
                 for seg in segments {
-                    s.push_str(self.operand(seg, locals).unwrap_str());
+                    s.push_str(self.operand(seg, locals)?.unwrap_str());
                 }
                 Value::Str(s.into())
             }
             RValue::BuildArray(segments) => {
                 let array = Array::default();
                 for (elem, repeat) in segments {
-                    let elem = self.operand(elem, locals);
-                    let repeat = repeat
-                        .as_ref()
-                        .map_or(1, |repeat| self.operand(repeat, locals).unwrap_int_usize());
+                    let elem = self.operand(elem, locals)?;
+                    let repeat = match repeat {
+                        Some(repeat) => self.operand(repeat, locals)?.unwrap_int_usize(),
+                        None => 1,
+                    };
                     array.extend(elem, repeat);
                 }
                 Value::Array(array)
             }
-            RValue::Use(operand) => self.operand(operand, locals),
+            RValue::Use(operand) => self.operand(operand, locals)?,
             RValue::Call { function, args } => {
-                let call_body = self.operand(function, locals).unwrap_fn();
-                let args = args.iter().map(|arg| self.operand(arg, locals)).collect();
-                self.run(call_body, args)
+                let call_body = self.operand(function, locals)?.unwrap_fn();
+                let args =
+                    args.iter().map(|arg| self.operand(arg, locals)).collect::<Result<_>>()?;
+                self.run(call_body, args)?
             }
             RValue::Binary { lhs, op, rhs } => {
-                let lhs = self.operand(lhs, locals);
-                let rhs = self.operand(rhs, locals);
-                binary_op(lhs, *op, rhs)
+                let lhs = self.operand(lhs, locals)?;
+                let rhs = self.operand(rhs, locals)?;
+                binary_op(lhs, *op, rhs)?
             }
-            RValue::Unary { op, operand } => unary_op(*op, self.operand(operand, locals), self.w),
-        }
+            RValue::Unary { op, operand } => {
+                let operand = self.operand(operand, locals)?;
+                unary_op(*op, operand, self.w)?
+            }
+        })
     }
 
-    fn operand(&self, operand: &Operand, locals: &Places) -> Value {
-        match operand {
-            Operand::Ref(place) => Value::Ref(self.load_place(place, locals)),
+    fn operand(&self, operand: &Operand, locals: &Places) -> Result<Value> {
+        Ok(match operand {
+            Operand::Ref(place) => Value::Ref(self.load_place(place, locals)?),
             Operand::Constant(constant) => const_value(constant),
-            Operand::Place(place) => self.load_place(place, locals).clone_raw(),
-        }
+            Operand::Place(place) => self.load_place(place, locals)?.clone_raw(),
+            Operand::Unreachable => internal_error!("read the result of a diverging expression"),
+        })
     }
 
     #[expect(clippy::unused_self)]
-    fn load_place(&self, place: &Place, locals: &Places) -> Allocation {
+    fn load_place(&self, place: &Place, locals: &Places) -> Result<Allocation> {
         let mut alloc = locals[place.local].clone();
         for projection in &place.projections {
             alloc = match *projection {
                 Projection::Deref => alloc.borrow().unwrap_ref().clone(),
                 Projection::Field(field) => alloc.borrow().unwrap_struct()[field as usize].clone(),
                 Projection::Index(index) => {
-                    let index = locals[index].borrow().unwrap_int_usize();
-                    alloc.borrow().unwrap_array().get(index).unwrap().clone()
+                    let index = locals[index].borrow().unwrap_int();
+                    index_array(alloc.borrow().unwrap_array(), index)?
                 }
                 Projection::ConstantIndex(index) => {
-                    alloc.borrow().unwrap_array().get(index as _).unwrap().clone()
+                    index_array(alloc.borrow().unwrap_array(), index.into())?
                 }
             };
         }
-        alloc
+        Ok(alloc)
     }
 }
 
+fn index_array(array: &Array, index: i64) -> Result<Allocation> {
+    let elem = usize::try_from(index).ok().and_then(|i| array.get(i));
+    elem.ok_or_else(|| RuntimeError::IndexOutOfBounds { index, len: array.len() })
+}
+
 #[expect(clippy::needless_pass_by_value)]
-pub fn unary_op(op: UnaryOp, operand: Value, w: &mut dyn Write) -> Value {
-    match op {
+pub fn unary_op(op: UnaryOp, operand: Value, w: &mut dyn Write) -> Result<Value> {
+    Ok(match op {
         UnaryOp::ArrayStrFmt => {
             let mut string = String::new();
             string.push('[');
@@ -161,14 +309,24 @@ pub fn unary_op(op: UnaryOp, operand: Value, w: &mut dyn Write) -> Value {
             Value::Str(string.into())
         }
         UnaryOp::ArrayLen => Value::Int(operand.unwrap_ref_array().len().try_into().unwrap()),
-        UnaryOp::ArrayPop => operand.unwrap_ref_array().pop(),
+        UnaryOp::ArrayPop => operand.unwrap_ref_array().pop().ok_or(RuntimeError::PopEmptyArray)?,
+        UnaryOp::ArraySort => operand.unwrap_ref_array().sort().into(),
+        UnaryOp::ArrayReverse => operand.unwrap_ref_array().reverse().into(),
 
         UnaryOp::BoolNot => Value::Bool(!operand.unwrap_bool()),
         UnaryOp::BoolToStr => Value::Str(bool_to_str(operand.unwrap_bool())),
 
-        UnaryOp::IntNeg => Value::Int(-operand.unwrap_int()),
+        UnaryOp::IntNeg => {
+            Value::Int(operand.unwrap_int().checked_neg().ok_or(RuntimeError::Overflow)?)
+        }
         UnaryOp::IntToStr => Value::Str(operand.unwrap_int().to_string().into()),
-        UnaryOp::Chr => Value::Char(u8::try_from(operand.unwrap_int()).unwrap() as char),
+        UnaryOp::IntToHex => Value::Str(format!("{:x}", operand.unwrap_int()).into()),
+        UnaryOp::IntToBin => Value::Str(format!("{:b}", operand.unwrap_int()).into()),
+        UnaryOp::Chr => {
+            let code = operand.unwrap_int();
+            let char = u32::try_from(code).ok().and_then(char::from_u32);
+            Value::Char(char.ok_or(RuntimeError::InvalidChar(code))?)
+        }
 
         UnaryOp::Ord => Value::Int(i64::from(u32::from(operand.unwrap_char()))),
         UnaryOp::CharToStr => Value::Str(operand.unwrap_char().to_string().into()),
@@ -177,15 +335,28 @@ pub fn unary_op(op: UnaryOp, operand: Value, w: &mut dyn Write) -> Value {
             _ = write!(w, "{}", operand.unwrap_str());
             Value::Unit
         }
+        UnaryOp::Println => {
+            _ = writeln!(w, "{}", operand.unwrap_str());
+            Value::Unit
+        }
         UnaryOp::StrLen => Value::Int(operand.unwrap_str().len().try_into().unwrap()),
+        UnaryOp::StrCharLen => Value::Int(operand.unwrap_str().chars().count().try_into().unwrap()),
 
-        UnaryOp::RangeToStr => {
-            let Range { start, end } = operand.unwrap_range();
-            Value::Str(arcstr::format!("{start}..{end}"))
+        UnaryOp::RangeToStr => Value::Str(arcstr::format!("{}", operand.unwrap_range())),
+        UnaryOp::CharRangeToStr => {
+            let range = operand.unwrap_range();
+            // both ends are chars the range was written with
+            let [start, end] = [range.start, range.displayed_end()]
+                .map(|c| char::from_u32(c.try_into().unwrap()).unwrap());
+            Value::Str(arcstr::format!("{start}{}{end}", range.separator()))
         }
         UnaryOp::RangeStart => Value::Int(operand.unwrap_range().start),
         UnaryOp::RangeEnd => Value::Int(operand.unwrap_range().end),
-    }
+
+        UnaryOp::FnToStr => Value::Str(arcstr::format!("<fn#{}>", operand.unwrap_fn().index())),
+
+        UnaryOp::SetLen => Value::Int(operand.unwrap_ref_set().len().try_into().unwrap()),
+    })
 }
 
 fn bool_to_str(bool: bool) -> ArcStr {
@@ -193,45 +364,100 @@ fn bool_to_str(bool: bool) -> ArcStr {
 }
 
 #[expect(clippy::needless_pass_by_value)]
-pub fn binary_op(lhs: Value, op: BinaryOp, rhs: Value) -> Value {
-    match op {
+pub fn binary_op(lhs: Value, op: BinaryOp, rhs: Value) -> Result<Value> {
+    use RuntimeError::{DivideByZero, Overflow};
+
+    Ok(match op {
         BinaryOp::ArrayPush => lhs.unwrap_ref_array().push(rhs).into(),
-        BinaryOp::IntAdd => Value::Int(lhs.unwrap_int() + rhs.unwrap_int()),
-        BinaryOp::IntSub => Value::Int(lhs.unwrap_int() - rhs.unwrap_int()),
-        BinaryOp::IntMul => Value::Int(lhs.unwrap_int() * rhs.unwrap_int()),
-        BinaryOp::IntDiv => Value::Int(lhs.unwrap_int() / rhs.unwrap_int()),
-        BinaryOp::IntMod => Value::Int(lhs.unwrap_int() % rhs.unwrap_int()),
+        BinaryOp::IntAdd => {
+            Value::Int(lhs.unwrap_int().checked_add(rhs.unwrap_int()).ok_or(Overflow)?)
+        }
+        BinaryOp::IntSub => {
+            Value::Int(lhs.unwrap_int().checked_sub(rhs.unwrap_int()).ok_or(Overflow)?)
+        }
+        BinaryOp::IntMul => {
+            Value::Int(lhs.unwrap_int().checked_mul(rhs.unwrap_int()).ok_or(Overflow)?)
+        }
+        BinaryOp::IntDiv | BinaryOp::IntMod if rhs.unwrap_int() == 0 => return Err(DivideByZero),
+        BinaryOp::IntDiv => {
+            Value::Int(lhs.unwrap_int().checked_div(rhs.unwrap_int()).ok_or(Overflow)?)
+        }
+        BinaryOp::IntMod => {
+            Value::Int(lhs.unwrap_int().checked_rem(rhs.unwrap_int()).ok_or(Overflow)?)
+        }
         BinaryOp::IntLess => Value::Bool(lhs.unwrap_int() < rhs.unwrap_int()),
         BinaryOp::IntGreater => Value::Bool(lhs.unwrap_int() > rhs.unwrap_int()),
         BinaryOp::IntLessEq => Value::Bool(lhs.unwrap_int() <= rhs.unwrap_int()),
         BinaryOp::IntGreaterEq => Value::Bool(lhs.unwrap_int() >= rhs.unwrap_int()),
         BinaryOp::IntEq => Value::Bool(lhs.unwrap_int() == rhs.unwrap_int()),
         BinaryOp::IntNeq => Value::Bool(lhs.unwrap_int() != rhs.unwrap_int()),
-        BinaryOp::IntRange => Value::Range(Box::new(lhs.unwrap_int()..rhs.unwrap_int())),
-        BinaryOp::IntRangeInclusive =>
-        {
-            #[expect(clippy::range_plus_one)]
-            Value::Range(Box::new(lhs.unwrap_int()..rhs.unwrap_int() + 1))
+        BinaryOp::IntRange => {
+            let (start, end) = (lhs.unwrap_int(), rhs.unwrap_int());
+            Value::Range(Box::new(IntRange { start, end, inclusive: false }))
+        }
+        BinaryOp::IntRangeInclusive => {
+            let (start, end) = (lhs.unwrap_int(), rhs.unwrap_int().checked_add(1).ok_or(Overflow)?);
+            Value::Range(Box::new(IntRange { start, end, inclusive: true }))
         }
 
+        BinaryOp::ByteAdd => Value::Byte(lhs.unwrap_byte().wrapping_add(rhs.unwrap_byte())),
+        BinaryOp::ByteSub => Value::Byte(lhs.unwrap_byte().wrapping_sub(rhs.unwrap_byte())),
+        BinaryOp::ByteEq => Value::Bool(lhs.unwrap_byte() == rhs.unwrap_byte()),
+        BinaryOp::ByteNeq => Value::Bool(lhs.unwrap_byte() != rhs.unwrap_byte()),
+
+        BinaryOp::BoolEq => Value::Bool(lhs.unwrap_bool() == rhs.unwrap_bool()),
+        BinaryOp::BoolNeq => Value::Bool(lhs.unwrap_bool() != rhs.unwrap_bool()),
+
         BinaryOp::CharEq => Value::Bool(lhs.unwrap_char() == rhs.unwrap_char()),
         BinaryOp::CharNeq => Value::Bool(lhs.unwrap_char() != rhs.unwrap_char()),
 
         BinaryOp::StrEq => Value::Bool(lhs.unwrap_str() == rhs.unwrap_str()),
         BinaryOp::StrNeq => Value::Bool(lhs.unwrap_str() != rhs.unwrap_str()),
+        BinaryOp::StrLess => Value::Bool(lhs.unwrap_str() < rhs.unwrap_str()),
+        BinaryOp::StrGreater => Value::Bool(lhs.unwrap_str() > rhs.unwrap_str()),
+        BinaryOp::StrLessEq => Value::Bool(lhs.unwrap_str() <= rhs.unwrap_str()),
+        BinaryOp::StrGreaterEq => Value::Bool(lhs.unwrap_str() >= rhs.unwrap_str()),
         BinaryOp::StrAdd => Value::Str((lhs.unwrap_str().to_string() + rhs.unwrap_str()).into()),
         BinaryOp::StrIndex => {
-            Value::Char(lhs.unwrap_str().as_bytes()[rhs.unwrap_int_usize()] as char)
+            let (str, index) = (lhs.unwrap_str(), rhs.unwrap_int());
+            let byte = usize::try_from(index).ok().and_then(|i| str.as_bytes().get(i));
+            let len = str.len();
+            Value::Char(*byte.ok_or(RuntimeError::IndexOutOfBounds { index, len })? as char)
+        }
+        BinaryOp::StrIndexSlice => {
+            let (str, IntRange { start, end, .. }) = (lhs.unwrap_str(), rhs.unwrap_range());
+            let range = usize::try_from(start).ok().zip(usize::try_from(end).ok());
+            // `get` also fails on a range that would split a character
+            let slice = range.and_then(|(start, end)| str.get(start..end));
+            let len = str.len();
+            Value::Str(slice.ok_or(RuntimeError::SliceOutOfBounds { start, end, len })?.into())
+        }
+        BinaryOp::StrFind => {
+            let found = lhs.unwrap_str().find(rhs.unwrap_str().as_str());
+            Value::Int(found.ok_or(RuntimeError::SubstringNotFound)?.try_into().unwrap())
+        }
+        BinaryOp::StrRFind => {
+            let found = lhs.unwrap_str().rfind(rhs.unwrap_str().as_str());
+            Value::Int(found.ok_or(RuntimeError::SubstringNotFound)?.try_into().unwrap())
         }
-        BinaryOp::StrIndexSlice => Value::Str(lhs.unwrap_str()[rhs.unwrap_range_usize()].into()),
-        BinaryOp::StrFind => Value::Int(
-            lhs.unwrap_str().find(rhs.unwrap_str().as_str()).unwrap().try_into().unwrap(),
-        ),
-        BinaryOp::StrRFind => Value::Int(
-            lhs.unwrap_str().rfind(rhs.unwrap_str().as_str()).unwrap().try_into().unwrap(),
-        ),
         BinaryOp::ArrayIndexRange => todo!(),
-    }
+        BinaryOp::ArrayContains => Value::Bool(lhs.unwrap_ref_array().contains(&rhs)),
+        BinaryOp::ArrayEq | BinaryOp::StructEq => Value::Bool(lhs.structural_eq(&rhs)),
+        BinaryOp::ArrayNeq | BinaryOp::StructNeq => Value::Bool(!lhs.structural_eq(&rhs)),
+        BinaryOp::StructLess => Value::Bool(lhs.structural_cmp(&rhs).is_lt()),
+        BinaryOp::StructGreater => Value::Bool(lhs.structural_cmp(&rhs).is_gt()),
+        BinaryOp::StructLessEq => Value::Bool(lhs.structural_cmp(&rhs).is_le()),
+        BinaryOp::StructGreaterEq => Value::Bool(lhs.structural_cmp(&rhs).is_ge()),
+        BinaryOp::FnEq => Value::Bool(lhs.unwrap_fn() == rhs.unwrap_fn()),
+        BinaryOp::FnNeq => Value::Bool(lhs.unwrap_fn() != rhs.unwrap_fn()),
+
+        BinaryOp::MapEntry => Value::Ref(lhs.unwrap_ref_map().entry(rhs)),
+        BinaryOp::MapGet => lhs.unwrap_ref_map().get(&rhs).ok_or(RuntimeError::KeyNotFound)?,
+        BinaryOp::MapContains => Value::Bool(lhs.unwrap_ref_map().contains(&rhs)),
+
+        BinaryOp::SetAdd => Value::Bool(lhs.unwrap_ref_set().add(rhs)),
+        BinaryOp::SetContains => Value::Bool(lhs.unwrap_ref_set().contains(&rhs)),
+    })
 }
 
 pub fn const_value(constant: &Constant) -> Value {
@@ -241,9 +467,12 @@ pub fn const_value(constant: &Constant) -> Value {
         ),
         Constant::Unit => Value::Unit,
         Constant::EmptyArray { cap } => Value::Array(Array::with_capacity(cap)),
+        Constant::EmptyMap => Value::Map(Map::default()),
+        Constant::EmptySet => Value::Set(Set::default()),
         Constant::Bool(bool) => Value::Bool(bool),
         Constant::Int(int) => Value::Int(int),
-        Constant::Range(ref range) => Value::Range(Box::new(range.clone())),
+        Constant::Byte(byte) => Value::Byte(byte),
+        Constant::Range(range) => Value::Range(Box::new(range)),
         Constant::Char(char) => Value::Char(char),
         Constant::Str(ref str) => Value::Str(str.clone()),
         Constant::Func(body) => Value::Fn(body),
@@ -0,0 +1,28 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use super::Value;
+use crate::HashSet;
+
+#[derive(Clone, Default)]
+pub struct Set {
+    inner: Rc<RefCell<HashSet<Value>>>,
+}
+
+impl Set {
+    // returns whether `value` wasn't already in the set
+    pub fn add(&self, value: Value) -> bool {
+        self.inner.borrow_mut().insert(value)
+    }
+    pub fn contains(&self, value: &Value) -> bool {
+        self.inner.borrow().contains(value)
+    }
+    pub fn len(&self) -> usize {
+        self.inner.borrow().len()
+    }
+}
+
+impl fmt::Debug for Set {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.inner.borrow().fmt(f)
+    }
+}
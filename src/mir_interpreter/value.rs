@@ -1,14 +1,15 @@
 use std::{
     cell::{RefCell, RefMut},
-    ops::Range,
+    cmp::Ordering,
+    hash::{Hash, Hasher},
     rc::Rc,
 };
 
 use arcstr::ArcStr;
 use thin_vec::ThinVec;
 
-use super::array::Array;
-use crate::mir::BodyId;
+use super::{array::Array, map::Map, set::Set};
+use crate::mir::{BodyId, IntRange};
 
 #[derive(Debug, Clone)]
 pub struct Allocation {
@@ -25,6 +26,15 @@ impl Allocation {
     pub fn clone_raw(&self) -> Value {
         self.inner.borrow().clone()
     }
+    pub fn structural_eq(&self, other: &Self) -> bool {
+        self.inner.borrow().structural_eq(&other.inner.borrow())
+    }
+    pub fn eq_value(&self, value: &Value) -> bool {
+        self.inner.borrow().structural_eq(value)
+    }
+    pub fn structural_cmp(&self, other: &Self) -> Ordering {
+        self.inner.borrow().structural_cmp(&other.inner.borrow())
+    }
 }
 
 impl From<Value> for Allocation {
@@ -37,9 +47,12 @@ impl From<Value> for Allocation {
 pub enum Value {
     Unit,
     Array(Array),
+    Map(Map),
+    Set(Set),
     Bool(bool),
     Int(i64),
-    Range(Box<Range<i64>>),
+    Byte(u8),
+    Range(Box<IntRange>),
     Char(char),
     Str(ArcStr),
     Fn(BodyId),
@@ -53,12 +66,15 @@ impl From<()> for Value {
     }
 }
 
+// struct fields are copied into fresh allocations, but a `Ref` shares the allocation it
+// points to, so cloning never follows a reference and terminates even for cyclic values
 impl Clone for Value {
     fn clone(&self) -> Self {
         match *self {
             Self::Unit => Self::Unit,
             Self::Bool(bool) => Self::Bool(bool),
             Self::Int(int) => Self::Int(int),
+            Self::Byte(byte) => Self::Byte(byte),
             Self::Char(char) => Self::Char(char),
             Self::Fn(func) => Self::Fn(func),
             Self::Str(ref str) => Self::Str(str.clone()),
@@ -68,20 +84,77 @@ impl Clone for Value {
             }
             Self::Ref(ref inner) => Self::Ref(inner.clone()),
             Self::Array(ref array) => Self::Array(array.clone()),
+            Self::Map(ref map) => Self::Map(map.clone()),
+            Self::Set(ref set) => Self::Set(set.clone()),
         }
     }
 }
 
+// only scalar values can be used as map keys or set elements
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            Self::Int(int) => int.hash(state),
+            Self::Byte(byte) => byte.hash(state),
+            Self::Char(char) => char.hash(state),
+            Self::Str(ref str) => str.hash(state),
+            ref other => internal_error!("cannot hash {other:?}"),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.structural_eq(other)
+    }
+}
+
+impl Eq for Value {}
+
 macro_rules! value {
     ($ty:ident, $value: expr) => {{
         match $value {
             Value::$ty(out) => out,
-            other => unreachable!("expected {}, found {other:?}", stringify!($ty)),
+            other => internal_error!("expected {}, found {other:?}", stringify!($ty)),
         }
     }};
 }
 
 impl Value {
+    // compares by value, following references and comparing structs and arrays element-wise
+    pub fn structural_eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Unit, Self::Unit) => true,
+            (Self::Bool(lhs), Self::Bool(rhs)) => lhs == rhs,
+            (Self::Int(lhs), Self::Int(rhs)) => lhs == rhs,
+            (Self::Byte(lhs), Self::Byte(rhs)) => lhs == rhs,
+            (Self::Char(lhs), Self::Char(rhs)) => lhs == rhs,
+            (Self::Str(lhs), Self::Str(rhs)) => lhs == rhs,
+            (Self::Range(lhs), Self::Range(rhs)) => lhs == rhs,
+            (Self::Fn(lhs), Self::Fn(rhs)) => lhs == rhs,
+            (Self::Array(lhs), Self::Array(rhs)) => lhs.structural_eq(rhs),
+            (Self::Struct(lhs), Self::Struct(rhs)) => {
+                lhs.len() == rhs.len() && lhs.iter().zip(rhs).all(|(l, r)| l.structural_eq(r))
+            }
+            (Self::Ref(lhs), Self::Ref(rhs)) => lhs.structural_eq(rhs),
+            (lhs, rhs) => internal_error!("cannot compare {lhs:?} with {rhs:?}"),
+        }
+    }
+    // orders values of the same type, only defined for types that implement `Ord`
+    pub fn structural_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Int(lhs), Self::Int(rhs)) => lhs.cmp(rhs),
+            (Self::Byte(lhs), Self::Byte(rhs)) => lhs.cmp(rhs),
+            (Self::Char(lhs), Self::Char(rhs)) => lhs.cmp(rhs),
+            (Self::Str(lhs), Self::Str(rhs)) => lhs.cmp(rhs),
+            // fields are compared in declaration order
+            (Self::Struct(lhs), Self::Struct(rhs)) => (lhs.iter().zip(rhs))
+                .map(|(lhs, rhs)| lhs.structural_cmp(rhs))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal),
+            (lhs, rhs) => internal_error!("cannot order {lhs:?} and {rhs:?}"),
+        }
+    }
     pub fn unwrap_ref(&self) -> &Allocation {
         value!(Ref, self)
     }
@@ -96,18 +169,17 @@ impl Value {
         let int = self.unwrap_int();
         int.try_into().unwrap_or_else(|_| panic!("{int}"))
     }
+    pub fn unwrap_byte(&self) -> u8 {
+        *value!(Byte, self)
+    }
     pub fn unwrap_char(&self) -> char {
         *value!(Char, self)
     }
     pub fn unwrap_str(&self) -> &ArcStr {
         value!(Str, self)
     }
-    pub fn unwrap_range(&self) -> &Range<i64> {
-        value!(Range, self)
-    }
-    pub fn unwrap_range_usize(&self) -> Range<usize> {
-        let range = self.unwrap_range();
-        usize::try_from(range.start).unwrap()..usize::try_from(range.end).unwrap()
+    pub fn unwrap_range(&self) -> IntRange {
+        **value!(Range, self)
     }
     pub fn unwrap_fn(&self) -> BodyId {
         *value!(Fn, self)
@@ -122,4 +194,12 @@ impl Value {
     pub fn unwrap_ref_array(&self) -> Array {
         self.unwrap_ref().borrow().unwrap_array().clone()
     }
+    pub fn unwrap_ref_map(&self) -> Map {
+        let value = self.unwrap_ref().borrow();
+        value!(Map, &*value).clone()
+    }
+    pub fn unwrap_ref_set(&self) -> Set {
+        let value = self.unwrap_ref().borrow();
+        value!(Set, &*value).clone()
+    }
 }
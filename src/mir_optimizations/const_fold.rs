@@ -23,12 +23,13 @@ pub fn try_compute(rvalue: &RValue) -> Option<Operand> {
         RValue::Binary { lhs, op, rhs } => {
             let lhs = value_of(lhs)?;
             let rhs = value_of(rhs)?;
-            let value = mir_interpreter::binary_op(lhs, *op, rhs);
+            // a runtime error is left to happen at runtime
+            let value = mir_interpreter::binary_op(lhs, *op, rhs).ok()?;
             constant_of(&value)
         }
         RValue::Unary { op, operand } => {
             let operand = value_of(operand)?;
-            let value = mir_interpreter::unary_op(*op, operand, &mut std::io::sink());
+            let value = mir_interpreter::unary_op(*op, operand, &mut std::io::sink()).ok()?;
             constant_of(&value)
         }
         RValue::StrJoin(segments) => {
@@ -61,9 +62,10 @@ pub fn constant_of(value: &Value) -> Option<Operand> {
         Value::Unit => Constant::Unit,
         Value::Bool(bool) => Constant::Bool(bool),
         Value::Int(int) => Constant::Int(int),
+        Value::Byte(byte) => Constant::Byte(byte),
         Value::Char(char) => Constant::Char(char),
         Value::Str(ref str) => Constant::Str(str.as_str().into()),
-        Value::Range(ref range) => Constant::Range((**range).clone()),
+        Value::Range(ref range) => Constant::Range(**range),
         Value::Array(ref array) if array.is_empty() => {
             Constant::EmptyArray { cap: array.capacity() }
         }
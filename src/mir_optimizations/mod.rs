@@ -19,7 +19,7 @@ mod remove_dead_blocks;
 mod remove_dead_places;
 mod remove_goto_terminator;
 mod remove_unreachable;
-mod utils;
+pub mod utils;
 
 pub fn optimize(mir: &mut Mir, opts: &CodegenOpts, v: u8) {
     for body in 0..mir.bodies.len() {
@@ -20,7 +20,9 @@ pub fn optimize(mir: &mut Mir, body_id: mir::BodyId) {
     }
     for block in &mut body.blocks {
         block.terminator.with_jumps_mut(|jump| {
-            while let Some(next) = replacements.get(jump) {
+            // empty blocks can jump in a cycle, as in `loop {}`, so bound the chain
+            for _ in 0..replacements.len() {
+                let Some(next) = replacements.get(jump) else { break };
                 *jump = *next;
             }
         });
@@ -4,10 +4,7 @@ use super::{
     Parse, Stream, parse_atom_with,
     token::{Token, TokenKind},
 };
-use crate::{
-    ast::{BinOpKind, BinaryOp, ExprId, ExprKind, UnaryOp},
-    source::span::Span,
-};
+use crate::ast::{BinOpKind, BinaryOp, ExprId, ExprKind, Identifier, UnaryOp};
 
 impl Parse for ExprId {
     fn parse(stream: &mut Stream) -> Result<Self> {
@@ -45,7 +42,7 @@ fn parse_expr(stream: &mut Stream, precedence: u8) -> Result<ExprId> {
     };
     let mut root = parse_expr(stream, precedence + 1)?;
     loop {
-        let Some(token) = stream.lexer.clone().next() else { break };
+        let Some(token) = stream.try_peek() else { break };
         let Ok(op) = BinaryOp::try_from(token) else { break };
         if !ops.contains(&op.kind) {
             break;
@@ -63,30 +60,35 @@ fn parse_leaf_expr(stream: &mut Stream, next: Token) -> Result<ExprId> {
     let mut expr = parse_atom_with(stream, next)?;
 
     loop {
-        let Some(token) = stream.lexer.clone().next() else { break };
+        let Some(token) = stream.try_peek() else { break };
         match token.kind {
+            // a parenthesized expr after `if`, `for`, etc. starts a new statement
+            TokenKind::LParen if is_block_like(&stream.ast.exprs[expr].kind) => break,
             TokenKind::LParen => {
                 _ = stream.next();
                 let args = stream.parse_separated(TokenKind::Comma, TokenKind::RParen)?;
-                let end = stream.lexer.current_pos();
-                let span = stream.ast.exprs[expr].span.start()..end;
+                let span = stream.span_from(stream.ast.exprs[expr].span);
                 expr = (stream.ast.exprs)
                     .push((ExprKind::FnCall { function: expr, args }).with_span(span));
             }
             TokenKind::Dot => 'block: {
                 _ = stream.next();
-                let field = stream.parse()?;
+                // tuple fields are named by their index, as in `pair.0`
+                let field = if stream.peek()?.kind == TokenKind::Int {
+                    let span = stream.next()?.span;
+                    Identifier { symbol: stream.lexer.src()[span].into(), span }
+                } else {
+                    stream.parse()?
+                };
                 if stream.peek()?.kind != TokenKind::LParen {
-                    let end = stream.lexer.current_pos();
-                    let span = stream.ast.exprs[expr].span.start()..end;
+                    let span = stream.span_from(stream.ast.exprs[expr].span);
                     expr = (stream.ast.exprs)
                         .push((ExprKind::FieldAccess { expr, field }).with_span(span));
                     break 'block;
                 }
                 _ = stream.next();
                 let args = stream.parse_separated(TokenKind::Comma, TokenKind::RParen)?;
-                let end = stream.lexer.current_pos();
-                let span = stream.ast.exprs[expr].span.start()..end;
+                let span = stream.span_from(stream.ast.exprs[expr].span);
                 expr = (stream.ast.exprs)
                     .push((ExprKind::MethodCall { expr, method: field, args }).with_span(span));
             }
@@ -94,8 +96,7 @@ fn parse_leaf_expr(stream: &mut Stream, next: Token) -> Result<ExprId> {
                 _ = stream.next();
                 let index = stream.parse()?;
                 stream.expect(TokenKind::RBracket)?;
-                let end = stream.lexer.current_pos();
-                let span = stream.ast.exprs[expr].span.start()..end;
+                let span = stream.span_from(stream.ast.exprs[expr].span);
                 expr = stream.ast.exprs.push((ExprKind::Index { expr, index }).with_span(span));
             }
             _ => break,
@@ -111,14 +112,30 @@ fn parse_unary_expr(stream: &mut Stream) -> Result<ExprId> {
             let op = match kind {
                 TokenKind::Minus => UnaryOp::Neg,
                 TokenKind::Not => UnaryOp::Not,
+                TokenKind::Ampersand if stream.peek()?.kind == TokenKind::Mut => {
+                    _ = stream.next();
+                    UnaryOp::RefMut
+                }
                 TokenKind::Ampersand => UnaryOp::Ref,
                 TokenKind::Star => UnaryOp::Deref,
                 _ => unreachable!(),
             };
             let expr = parse_unary_expr(stream)?;
-            let span = Span::join([token.span, stream.ast.exprs[expr].span]);
+            let span = token.span.to(stream.ast.exprs[expr].span);
             Ok(stream.ast.exprs.push((ExprKind::Unary { op, expr }).with_span(span)))
         }
         _ => parse_leaf_expr(stream, token),
     }
 }
+
+fn is_block_like(kind: &ExprKind) -> bool {
+    matches!(
+        kind,
+        ExprKind::Block(..)
+            | ExprKind::If { .. }
+            | ExprKind::While { .. }
+            | ExprKind::Loop { .. }
+            | ExprKind::For { .. }
+            | ExprKind::Match { .. }
+    )
+}
@@ -1,8 +1,18 @@
+#[cfg(test)]
+use std::cell::Cell;
 use std::str::Chars;
 
 use super::token::{Token, TokenKind};
 use crate::span::Span;
 
+#[cfg(test)]
+thread_local! {
+    // every token lexed on this thread, lets tests see how often the parser re-lexes
+    pub static TOKENS_LEXED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Splits `src` into tokens. Every position it deals in, including token spans,
+/// is a byte offset into `src`.
 #[derive(Clone)]
 pub struct Lexer<'src> {
     src: &'src str,
@@ -14,30 +24,24 @@ impl<'src> Lexer<'src> {
     pub fn new(src: &'src str) -> Self {
         Self { src, token_start: 0, chars: src.chars() }
     }
-    #[track_caller]
-    pub fn bump(&mut self, bytes: usize) {
-        self.chars = self.chars.as_str()[bytes..].chars();
-    }
-    pub fn offset(&self) -> usize {
-        self.src.len() - self.chars.as_str().len()
-    }
+    /// Continues lexing from `bytes`, which must lie on a char boundary.
     pub fn set_offset(&mut self, bytes: usize) {
         self.chars = self.src[bytes..].chars();
     }
     pub const fn src(&self) -> &'src str {
         self.src
     }
+    /// The offset just past the last token lexed, trailing whitespace is only skipped by the
+    /// following call to `next`.
     #[expect(clippy::cast_possible_truncation)]
     pub fn current_pos(&self) -> u32 {
         (self.src.len() - self.chars.as_str().len()) as u32
     }
+    /// The span from the current position to the end of `src`.
     #[expect(clippy::cast_possible_truncation)]
     pub fn span_eof(&self) -> Span {
         Span::from(self.current_pos()..self.src.len() as u32)
     }
-    pub fn span(&self) -> Span {
-        Span::from(self.token_start..self.current_pos())
-    }
 
     fn try_next(&mut self, expected: char) -> bool {
         match self.chars.clone().next() {
@@ -64,6 +68,8 @@ impl Iterator for Lexer<'_> {
             }
         };
         self.token_start = self.current_pos() - char.len_utf8() as u32;
+        #[cfg(test)]
+        TOKENS_LEXED.with(|lexed| lexed.set(lexed.get() + 1));
         let kind = match char {
             // Longer Symbols
             '.' if self.try_next('.') => {
@@ -86,6 +92,7 @@ impl Iterator for Lexer<'_> {
             '<' if self.try_next('=') => TokenKind::LessEq,
             // Symbols
             '&' => TokenKind::Ampersand,
+            '|' => TokenKind::Pipe,
 
             '.' => TokenKind::Dot,
             ',' => TokenKind::Comma,
@@ -110,8 +117,16 @@ impl Iterator for Lexer<'_> {
             '>' => TokenKind::Greater,
             '<' => TokenKind::Less,
 
+            '\'' if self.is_label() => {
+                self.ident(self.token_start);
+                TokenKind::Label
+            }
             '\'' => self.char(),
-            '"' => self.str(),
+            '"' => self.str(false),
+            'r' if self.try_next('"') => {
+                self.str(true);
+                TokenKind::RawStr
+            }
             '0'..='9' => self.int(),
             'a'..='z' | 'A'..='Z' | '_' => self.ident(self.token_start),
             _ => TokenKind::Unknown,
@@ -135,37 +150,55 @@ impl Lexer<'_> {
         let Some(end) = self.chars.as_str().find("*/") else { return };
         self.chars = self.chars.as_str()[end + 2..].chars();
     }
+    // a quote followed by an identifier is a label unless it's closed like `'a'`
+    fn is_label(&self) -> bool {
+        let mut chars = self.chars.clone();
+        if !chars.next().is_some_and(|c| matches!(c, 'a'..='z' | 'A'..='Z' | '_')) {
+            return false;
+        }
+        chars.find(|&c| !matches!(c, 'a'..='z' | 'A'..='Z' | '_' | '0'..='9')) != Some('\'')
+    }
+    // lexes up to the closing quote, the parser checks that exactly one char was written
     fn char(&mut self) -> TokenKind {
-        if self.chars.next().is_some_and(|c| c == '\\') {
+        while let Some(next) = self.chars.clone().next() {
+            if next == '\n' {
+                break;
+            }
             self.chars.next();
+            match next {
+                '\'' => break,
+                '\\' => _ = self.chars.next(),
+                _ => {}
+            }
         }
-        self.chars.next();
         TokenKind::Char
     }
-    fn str(&mut self) -> TokenKind {
+    fn str(&mut self, raw: bool) -> TokenKind {
         while let Some(next) = self.chars.next() {
-            if next == '"' {
-                break;
-            }
-            if next == '$' && self.chars.clone().next().is_some_and(|c| c == '{') {
-                let mut d = 0;
-                for next in self.chars.by_ref() {
-                    match next {
-                        '{' => d += 1,
-                        '}' => d -= 1,
-                        _ => {}
-                    }
-                    if d == 0 {
-                        break;
-                    }
-                }
-            }
-            if next == '\\' && self.chars.next().is_some_and(|c| c == '\'') {
-                self.chars.next();
+            match next {
+                '"' => break,
+                '$' if self.try_next('{') => self.interpolation(),
+                '\\' if !raw => _ = self.chars.next(),
+                _ => {}
             }
         }
         TokenKind::Str
     }
+    // skips to the `}` closing a `${`, strings and chars inside may hold unbalanced braces
+    fn interpolation(&mut self) {
+        let mut depth = 1;
+        while let Some(next) = self.chars.next() {
+            match next {
+                '{' => depth += 1,
+                '}' if depth == 1 => return,
+                '}' => depth -= 1,
+                '"' => _ = self.str(false),
+                'r' if self.try_next('"') => _ = self.str(true),
+                '\'' => _ = self.char(),
+                _ => {}
+            }
+        }
+    }
     fn int(&mut self) -> TokenKind {
         while (self.chars.clone().next()).is_some_and(|c| c.is_numeric() || c == '_') {
             self.chars.next();
@@ -188,6 +221,7 @@ fn ident_kind(str: &str) -> TokenKind {
         "or" => TokenKind::Or,
         "trait" => TokenKind::Trait,
         "impl" => TokenKind::Impl,
+        "mut" => TokenKind::Mut,
         "unreachable" => TokenKind::Unreachable,
         "in" => TokenKind::In,
         "for" => TokenKind::For,
@@ -204,6 +238,7 @@ fn ident_kind(str: &str) -> TokenKind {
         "struct" => TokenKind::Struct,
         "true" => TokenKind::True,
         "while" => TokenKind::While,
+        "loop" => TokenKind::Loop,
         "match" => TokenKind::Match,
         _ => TokenKind::Ident,
     }
@@ -4,55 +4,137 @@ mod token;
 
 use std::path::Path;
 
-use lex::Lexer;
+pub use lex::Lexer;
+#[cfg(test)]
+pub use lex::TOKENS_LEXED;
 use miette::{Error, Result};
 use thin_vec::{ThinVec, thin_vec};
 use token::{Token, TokenKind};
 
 use crate::{
     ast::{
-        ArraySeg, Ast, BinOpKind, BinaryOp, Block, BlockId, Expr, ExprId, ExprKind, Field, FnDecl,
-        Identifier, IfStmt, Impl, Lit, MatchArm, Param, Pat, PatKind, Trait, Ty, TyKind, TypeId,
+        ArraySeg, Ast, BinOpKind, BinaryOp, Binding, Block, BlockId, Expr, ExprId, ExprKind, Field,
+        FnDecl, Generic, Identifier, IfStmt, Impl, Lit, MatchArm, Mutability, Param, Pat, PatKind,
+        Trait, Ty, TyKind, TypeId,
     },
     errors,
     span::Span,
     symbol::Symbol,
 };
 
-pub fn parse(src: &str, path: Option<&Path>) -> Result<Ast> {
-    let lexer = Lexer::new(src);
+pub fn parse(src: &str, path: Option<&Path>) -> Result<Ast, Vec<Error>> {
     let mut ast = Ast::default();
-    let mut stream = Stream { lexer, ast: &mut ast, path };
+    let mut stream = Stream::new(src, &mut ast, path);
     let mut top_level = vec![];
-    while let Some(next) = stream.lexer.clone().next() {
+    let mut errors = vec![];
+    while let Some(next) = stream.try_peek() {
         if next.kind == TokenKind::Semicolon {
-            _ = stream.lexer.next();
+            _ = stream.bump();
             continue;
         }
-        top_level.push(stream.parse()?);
+        let start = next.span.start();
+        match stream.parse() {
+            Ok(expr) => top_level.push(expr),
+            Err(err) => {
+                errors.push(err);
+                stream.seek(start as usize);
+                skip_item(&mut stream);
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
     }
     ast.top_level = top_level;
     Ok(ast)
 }
 
+// skips past the item at the start of `stream` so parsing can resume after a syntax error,
+// stopping after a `;` or closing `}` at the item's own depth, or before the next item.
+fn skip_item(stream: &mut Stream) {
+    let mut depth = 0usize;
+    let mut first = true;
+    while let Some(token) = stream.try_peek() {
+        let starts_item = matches!(
+            token.kind,
+            TokenKind::Fn
+                | TokenKind::Struct
+                | TokenKind::Trait
+                | TokenKind::Impl
+                | TokenKind::Const
+        );
+        if depth == 0 && starts_item && !first {
+            return;
+        }
+        first = false;
+        _ = stream.bump();
+        match token.kind {
+            TokenKind::LBrace => depth += 1,
+            TokenKind::RBrace if depth <= 1 => return,
+            TokenKind::RBrace => depth -= 1,
+            TokenKind::Semicolon if depth == 0 => return,
+            _ => {}
+        }
+    }
+}
+
 struct Stream<'src, 'path> {
     lexer: Lexer<'src>,
+    // a token already lexed by `peek`, the lexer sits just past it
+    peeked: Option<Token>,
+    // the end of the last token handed out by `next`
+    end: u32,
     ast: &'src mut Ast,
     path: Option<&'path Path>,
 }
 
-impl Stream<'_, '_> {
+impl<'src, 'path> Stream<'src, 'path> {
+    fn new(src: &'src str, ast: &'src mut Ast, path: Option<&'path Path>) -> Self {
+        Self { lexer: Lexer::new(src), peeked: None, end: 0, ast, path }
+    }
     fn next(&mut self) -> Result<Token> {
-        if let Some(result) = self.lexer.next() {
-            return Ok(result);
+        let token = self.bump().ok_or_else(|| self.handle_eof())?;
+        if token.kind == TokenKind::Unknown {
+            return Err(self.unknown_char(token));
         }
-        Err(self.handle_eof())
+        Ok(token)
+    }
+    // consumes the next token without checking that it's valid
+    fn bump(&mut self) -> Option<Token> {
+        let token = self.peeked.take().or_else(|| self.lexer.next())?;
+        self.end = token.span.end();
+        Some(token)
     }
-    fn clone(&mut self) -> Stream {
-        Stream { lexer: self.lexer.clone(), ast: self.ast, path: self.path }
+    fn clone(&mut self) -> Stream<'_, 'path> {
+        let Self { ref lexer, peeked, end, ref mut ast, path } = *self;
+        Stream { lexer: lexer.clone(), peeked, end, ast, path }
     }
     fn peek(&mut self) -> Result<Token> {
-        self.clone().next()
+        match self.try_peek() {
+            Some(token) if token.kind == TokenKind::Unknown => Err(self.unknown_char(token)),
+            Some(token) => Ok(token),
+            None => Err(self.handle_eof()),
+        }
+    }
+    fn try_peek(&mut self) -> Option<Token> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.next();
+        }
+        self.peeked
+    }
+    /// The byte offset just past the last consumed token.
+    const fn pos(&self) -> u32 {
+        self.end
+    }
+    /// The span from the start of `start` to the end of the last consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(Span::new(self.pos() as usize..self.pos() as usize, start.source()))
+    }
+    /// Moves the stream to a byte offset into the source, dropping any peeked token.
+    fn seek(&mut self, offset: usize) {
+        self.lexer.set_offset(offset);
+        self.peeked = None;
+        self.end = self.lexer.current_pos();
     }
     #[inline(never)]
     #[cold]
@@ -64,6 +146,17 @@ impl Stream<'_, '_> {
             [(self.lexer.span_eof(), "EOF")],
         )
     }
+    #[inline(never)]
+    #[cold]
+    fn unknown_char(&self, token: Token) -> miette::Error {
+        let char = &self.lexer.src()[token.span];
+        errors::error(
+            &format!("unknown character `{char}`"),
+            self.path,
+            self.lexer.src(),
+            [(token.span, "not a valid token")],
+        )
+    }
     fn expect(&mut self, kind: TokenKind) -> Result<Token> {
         let token = self.next()?;
         if token.kind != kind {
@@ -71,7 +164,7 @@ impl Stream<'_, '_> {
                 &format!("expected `{}`, found: `{}`", kind.repr(), token.kind.repr()),
                 self.path,
                 self.lexer.src(),
-                [(self.lexer.span(), "here")],
+                [(token.span, "here")],
             ));
         }
         Ok(token)
@@ -81,23 +174,18 @@ impl Stream<'_, '_> {
         if toks.contains(&token.kind) {
             return Ok(token);
         }
-        Err(self.any_failed(token, toks))
+        Err(self.any_failed(token, toks, token.span))
     }
     #[inline(never)]
     #[cold]
-    fn any_failed(&self, found: Token, toks: &[TokenKind]) -> Error {
+    fn any_failed(&self, found: Token, toks: &[TokenKind], span: Span) -> Error {
+        let expected =
+            toks.iter().map(|kind| format!("`{}`", kind.repr())).collect::<Vec<_>>().join(" or ");
         errors::error(
-            &format!(
-                "expected one of {}, found `{}`",
-                toks.iter()
-                    .map(|kind| format!("`{}`", kind.repr()))
-                    .collect::<Vec<_>>()
-                    .join(" or "),
-                found.kind.repr()
-            ),
+            &format!("expected one of {expected}, found `{}`", found.kind.repr()),
             self.path,
             self.lexer.src(),
-            [(self.lexer.span(), "here")],
+            [(span, format!("expected {expected}"))],
         )
     }
 
@@ -113,10 +201,12 @@ impl Stream<'_, '_> {
             }
             let expr = self.parse()?;
             args.push(expr);
+            let end = self.pos();
             match self.next()? {
                 tok if tok.kind == term => break,
                 tok if tok.kind == sep => {}
-                found => return Err(self.any_failed(found, &[sep, term])),
+                // point at the gap where the separator is missing rather than at the next element
+                found => return Err(self.any_failed(found, &[sep, term], Span::from(end..end))),
             }
         }
         Ok(args)
@@ -136,7 +226,7 @@ impl Parse for Symbol {
 
 impl Parse for Block {
     fn parse(stream: &mut Stream) -> Result<Self> {
-        let start = stream.lexer.current_pos() - 1; // ugly hack to include lbrace in span.
+        let start = stream.pos() - 1; // ugly hack to include lbrace in span.
         let mut stmts = thin_vec![];
         let mut is_expr = false;
 
@@ -157,7 +247,7 @@ impl Parse for Block {
             }
         }
 
-        let span = Span::from(start..stream.lexer.current_pos());
+        let span = Span::from(start..stream.pos());
         Ok(Self { stmts, is_expr, span })
     }
 }
@@ -212,14 +302,25 @@ impl Parse for Ty {
                 stream.expect(TokenKind::RBracket)?;
                 TyKind::Array(of)
             }
-            TokenKind::LParen => {
-                stream.expect(TokenKind::RParen)?;
+            TokenKind::LParen if stream.peek()?.kind == TokenKind::RParen => {
+                _ = stream.next();
                 TyKind::Unit
             }
-            TokenKind::Ampersand => TyKind::Ref(stream.parse()?),
+            TokenKind::LParen => {
+                TyKind::Tuple(stream.parse_separated(TokenKind::Comma, TokenKind::RParen)?)
+            }
+            TokenKind::Ampersand => {
+                let mutability = if stream.peek()?.kind == TokenKind::Mut {
+                    _ = stream.next();
+                    Mutability::Mut
+                } else {
+                    Mutability::Not
+                };
+                TyKind::Ref(stream.parse()?, mutability)
+            }
             _ => unreachable!(),
         };
-        let end = stream.lexer.current_pos();
+        let end = stream.pos();
         Ok(Self { kind, span: Span::from(start..end) })
     }
 }
@@ -232,14 +333,38 @@ impl Parse for Impl {
         } else {
             ThinVec::new()
         };
+        let trait_ = {
+            let mut fork = stream.clone();
+            fork.next()?.kind == TokenKind::Ident && fork.next()?.kind == TokenKind::For
+        };
+        let trait_ = if trait_ {
+            let ident = stream.parse()?;
+            stream.expect(TokenKind::For)?;
+            Some(ident)
+        } else {
+            None
+        };
         let ty = stream.parse()?;
         stream.expect(TokenKind::LBrace)?;
-        let methods = parse_trait_methods(stream)?;
-        let methods = methods
-            .into_iter()
-            .map(|decl| stream.ast.exprs.push(ExprKind::FnDecl(decl).todo_span()))
-            .collect();
-        Ok(Self { generics, ty, methods })
+        let mut methods = ThinVec::new();
+        let mut consts = ThinVec::new();
+        loop {
+            let next = stream.any(&[TokenKind::Fn, TokenKind::Const, TokenKind::RBrace])?;
+            match next.kind {
+                TokenKind::Fn => {
+                    let decl = stream.parse()?;
+                    methods.push(stream.ast.exprs.push(ExprKind::FnDecl(decl).todo_span()));
+                }
+                TokenKind::Const => {
+                    let expr = parse_var(stream, next)?;
+                    stream.expect(TokenKind::Semicolon)?;
+                    consts.push(stream.ast.exprs.push(expr));
+                }
+                TokenKind::RBrace => break,
+                _ => unreachable!(),
+            }
+        }
+        Ok(Self { generics, trait_, ty, methods, consts })
     }
 }
 
@@ -309,7 +434,10 @@ fn parse_struct(stream: &mut Stream) -> Result<Expr> {
 }
 
 fn parse_var(stream: &mut Stream, let_tok: Token) -> Result<Expr> {
-    let ident = stream.parse()?;
+    let binding = match let_tok.kind {
+        TokenKind::Let => stream.parse()?,
+        _ => Binding::Ident(stream.parse()?),
+    };
     let tok = stream.any(&[TokenKind::Colon, TokenKind::Eq])?;
     let mut ty = None;
     if tok.kind == TokenKind::Colon {
@@ -317,44 +445,91 @@ fn parse_var(stream: &mut Stream, let_tok: Token) -> Result<Expr> {
         stream.expect(TokenKind::Eq)?;
     }
     let expr = stream.parse()?;
-    let span = Span::new(
-        let_tok.span.start() as _..stream.lexer.current_pos() as _,
-        let_tok.span.source(),
-    );
+    let span = stream.span_from(let_tok.span);
     Ok((match let_tok.kind {
-        TokenKind::Const => ExprKind::Const { ident, ty, expr },
-        TokenKind::Let => ExprKind::Let { ident, ty, expr },
+        TokenKind::Let => ExprKind::Let { binding, ty, expr },
+        TokenKind::Const => {
+            let Binding::Ident(ident) = binding else { unreachable!() };
+            ExprKind::Const { ident, ty, expr }
+        }
         _ => unreachable!(),
     })
     .with_span(span))
 }
 
-fn parse_while(stream: &mut Stream) -> Result<Expr> {
+// `loop`, `while` or `for`, after their label if they have one
+fn parse_loop(stream: &mut Stream, tok: Token, label: Option<Identifier>) -> Result<Expr> {
+    let start = label.map_or(tok.span, |label| label.span);
+    match tok.kind {
+        TokenKind::While => parse_while(stream, start, label),
+        TokenKind::For => parse_for(stream, label),
+        TokenKind::Loop => {
+            stream.expect(TokenKind::LBrace)?;
+            let block = stream.parse()?;
+            Ok(ExprKind::Loop { block, label }.with_span(stream.span_from(start)))
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn parse_label(stream: &mut Stream) -> Option<Identifier> {
+    let tok = stream.try_peek().filter(|tok| tok.kind == TokenKind::Label)?;
+    _ = stream.next();
+    Some(Identifier { symbol: Symbol::from(&stream.lexer.src()[tok.span]), span: tok.span })
+}
+
+fn parse_while(stream: &mut Stream, start: Span, label: Option<Identifier>) -> Result<Expr> {
+    if stream.peek()?.kind == TokenKind::Let {
+        return parse_whilelet(stream, start, label);
+    }
     let condition = stream.parse()?;
     stream.expect(TokenKind::LBrace)?;
     let block = stream.parse()?;
-    Ok((ExprKind::While { condition, block }).todo_span())
+    Ok((ExprKind::While { condition, block, label }).todo_span())
 }
 
-fn parse_for(stream: &mut Stream) -> Result<Expr> {
+// `while let pat = expr { .. }` is sugar for `loop { match expr { pat => { .. }, _ => break } }`
+fn parse_whilelet(stream: &mut Stream, start: Span, label: Option<Identifier>) -> Result<Expr> {
+    stream.expect(TokenKind::Let)?;
+    let pat = stream.parse()?;
+    stream.expect(TokenKind::Eq)?;
+    let scrutinee = stream.parse()?;
+    let lbrace = stream.expect(TokenKind::LBrace)?.span;
+    let block = stream.parse()?;
+    let span = stream.span_from(lbrace);
+    let body = stream.ast.exprs.push(ExprKind::Block(block).with_span(span));
+
+    let span = stream.span_from(start);
+    let exit = stream.ast.exprs.push(ExprKind::Break(None).with_span(span));
+    let catch_all = Pat { kind: PatKind::Ident(Symbol::from("_")), span };
+    let arms = thin_vec![MatchArm { pat, body }, MatchArm { pat: catch_all, body: exit }];
+    let stmts =
+        thin_vec![stream.ast.exprs.push(ExprKind::Match { scrutinee, arms }.with_span(span))];
+    let block = stream.ast.blocks.push(Block { stmts, is_expr: false, span });
+    Ok(ExprKind::Loop { block, label }.with_span(span))
+}
+
+fn parse_for(stream: &mut Stream, label: Option<Identifier>) -> Result<Expr> {
     let ident = stream.parse()?;
     stream.expect(TokenKind::In)?;
     let iter = stream.parse()?;
     stream.expect(TokenKind::LBrace)?;
     let body = stream.parse()?;
-    Ok((ExprKind::For { ident, iter, body }).todo_span())
+    Ok((ExprKind::For { ident, iter, body, label }).todo_span())
 }
 
 fn parse_match(stream: &mut Stream, tok: Token) -> Result<Expr> {
     let scrutinee = stream.parse()?;
     stream.expect(TokenKind::LBrace)?;
     let arms = stream.parse_separated(TokenKind::Comma, TokenKind::RBrace)?;
-    let end = stream.lexer.current_pos() as usize;
-    let span = Span::new(tok.span.start() as usize..end, tok.span.source());
+    let span = stream.span_from(tok.span);
     Ok((ExprKind::Match { scrutinee, arms }).with_span(span))
 }
 
 fn parse_ifchain(stream: &mut Stream, if_tok: Token) -> Result<Expr> {
+    if stream.peek()?.kind == TokenKind::Let {
+        return parse_iflet(stream, if_tok);
+    }
     let mut arms = thin_vec![];
     let els = loop {
         let condition = stream.parse()?;
@@ -365,18 +540,56 @@ fn parse_ifchain(stream: &mut Stream, if_tok: Token) -> Result<Expr> {
             break None;
         }
         _ = stream.next();
-        if stream.peek()?.kind == TokenKind::If {
-            _ = stream.next();
-        } else {
+        if stream.peek()?.kind != TokenKind::If {
             stream.expect(TokenKind::LBrace)?;
             break Some(stream.parse()?);
         }
+        let if_tok = stream.next()?;
+        if stream.peek()?.kind == TokenKind::Let {
+            // an `if let` ends the chain, becoming the body of its `else`
+            let expr = parse_iflet(stream, if_tok)?;
+            let span = expr.span;
+            let stmts = thin_vec![stream.ast.exprs.push(expr)];
+            break Some(stream.ast.blocks.push(Block { stmts, is_expr: true, span }));
+        }
     };
-    let end = stream.lexer.current_pos() as usize;
-    let span = Span::new(if_tok.span.start() as usize..end, if_tok.span.source());
+    let span = stream.span_from(if_tok.span);
     Ok((ExprKind::If { arms, els }).with_span(span))
 }
 
+// `if let pat = expr { .. } else { .. }` is sugar for a `match` with a catch-all `_` arm
+fn parse_iflet(stream: &mut Stream, if_tok: Token) -> Result<Expr> {
+    stream.expect(TokenKind::Let)?;
+    let pat = stream.parse()?;
+    stream.expect(TokenKind::Eq)?;
+    let scrutinee = stream.parse()?;
+    let block_expr = |stream: &mut Stream| -> Result<ExprId> {
+        let start = stream.expect(TokenKind::LBrace)?.span;
+        let block = stream.parse()?;
+        let span = stream.span_from(start);
+        Ok(stream.ast.exprs.push(ExprKind::Block(block).with_span(span)))
+    };
+    let body = block_expr(stream)?;
+    let els = if stream.try_peek().is_some_and(|tok| tok.kind == TokenKind::Else) {
+        _ = stream.next();
+        if stream.peek()?.kind == TokenKind::If {
+            let if_tok = stream.next()?;
+            let expr = parse_ifchain(stream, if_tok)?;
+            stream.ast.exprs.push(expr)
+        } else {
+            block_expr(stream)?
+        }
+    } else {
+        let span = Span::new(stream.pos() as usize..stream.pos() as usize, if_tok.span.source());
+        let block = stream.ast.blocks.push(Block { stmts: thin_vec![], is_expr: false, span });
+        stream.ast.exprs.push(ExprKind::Block(block).with_span(span))
+    };
+    let span = stream.span_from(if_tok.span);
+    let catch_all = Pat { kind: PatKind::Ident(Symbol::from("_")), span };
+    let arms = thin_vec![MatchArm { pat, body }, MatchArm { pat: catch_all, body: els }];
+    Ok((ExprKind::Match { scrutinee, arms }).with_span(span))
+}
+
 impl Parse for ArraySeg {
     fn parse(stream: &mut Stream) -> Result<Self> {
         let expr = stream.parse()?;
@@ -413,10 +626,7 @@ impl Parse for Pat {
                 }
                 _ => unreachable!(),
             };
-            let span = Span::new(
-                tok.span.start() as _..stream.lexer.current_pos() as _,
-                tok.span.source(),
-            );
+            let span = stream.span_from(tok.span);
             Ok(Pat { kind, span })
         }
         let single = parse_single(stream)?;
@@ -429,10 +639,7 @@ impl Parse for Pat {
             _ = stream.next();
             patterns.push(parse_single(stream)?);
         }
-        let span = Span::new(
-            single_span.start() as _..stream.lexer.current_pos() as _,
-            single_span.source(),
-        );
+        let span = stream.span_from(single_span);
         Ok(Pat { kind: PatKind::Or(patterns), span })
     }
 }
@@ -451,7 +658,12 @@ impl Parse for Param {
         let ident = stream.parse()?;
 
         let mut ty = None;
-        let next = stream.clone().any(&[TokenKind::Comma, TokenKind::Colon, TokenKind::RParen])?;
+        let next = stream.clone().any(&[
+            TokenKind::Comma,
+            TokenKind::Colon,
+            TokenKind::RParen,
+            TokenKind::Pipe,
+        ])?;
         if next.kind == TokenKind::Colon {
             _ = stream.next();
             ty = Some(stream.parse()?);
@@ -460,6 +672,16 @@ impl Parse for Param {
     }
 }
 
+impl Parse for Binding {
+    fn parse(stream: &mut Stream) -> Result<Self> {
+        if stream.peek()?.kind == TokenKind::LParen {
+            _ = stream.next();
+            return Ok(Self::Tuple(stream.parse_separated(TokenKind::Comma, TokenKind::RParen)?));
+        }
+        Ok(Self::Ident(stream.parse()?))
+    }
+}
+
 impl Parse for Field {
     fn parse(stream: &mut Stream) -> Result<Self> {
         let ident = stream.parse()?;
@@ -522,60 +744,81 @@ fn parse_atom_with(stream: &mut Stream, tok: Token) -> Result<ExprId> {
     }
     macro_rules! all {
         () => {
-            Span::from(tok.span.start()..stream.lexer.current_pos())
+            stream.span_from(tok.span)
         };
     }
 
     let expr = match tok.kind {
         TokenKind::Unreachable => Ok(ExprKind::Unreachable.with_span(tok.span)),
         TokenKind::LParen => {
-            return Ok(if stream.peek()?.kind == TokenKind::RParen {
+            if stream.peek()?.kind == TokenKind::RParen {
                 _ = stream.next();
-                stream.ast.exprs.push(ExprKind::Lit(Lit::Unit).todo_span())
-            } else {
-                let expr = stream.parse()?;
+                return Ok(stream.ast.exprs.push(ExprKind::Lit(Lit::Unit).todo_span()));
+            }
+            let expr = stream.parse()?;
+            // a trailing comma is what makes `(a,)` a tuple rather than a parenthesized expr
+            if stream.peek()?.kind != TokenKind::Comma {
                 stream.expect(TokenKind::RParen)?;
-                expr
-            });
+                return Ok(expr);
+            }
+            _ = stream.next();
+            let mut elems = thin_vec![expr];
+            elems.extend(stream.parse_separated::<ExprId>(TokenKind::Comma, TokenKind::RParen)?);
+            lit!(Lit::Tuple(elems), all!())
         }
         TokenKind::LBracket => Ok(ExprKind::Lit(Lit::Array {
             segments: stream.parse_separated(TokenKind::Comma, TokenKind::RBracket)?,
         })
-        .with_span(tok.span.start()..stream.lexer.current_pos())),
+        .with_span(stream.span_from(tok.span))),
         TokenKind::LBrace => Ok(ExprKind::Block(stream.parse()?).with_span(all!())),
-        TokenKind::Break => Ok(ExprKind::Break.with_span(tok.span)),
-        TokenKind::Continue => Ok(ExprKind::Continue.with_span(tok.span)),
+        TokenKind::Break => {
+            let label = parse_label(stream);
+            Ok(ExprKind::Break(label).with_span(stream.span_from(tok.span)))
+        }
+        TokenKind::Continue => {
+            let label = parse_label(stream);
+            Ok(ExprKind::Continue(label).with_span(stream.span_from(tok.span)))
+        }
         TokenKind::Assert => {
             let expr: ExprId = stream.parse()?;
-            Ok(ExprKind::Assert(expr).with_span(stream.ast.exprs[expr].span))
+            Ok(ExprKind::Assert(expr).with_span(stream.span_from(tok.span)))
         }
         TokenKind::Return => {
-            if (stream.lexer.clone().next()).is_none_or(|tok| tok.kind.is_terminator()) {
+            if stream.try_peek().is_none_or(|tok| tok.kind.is_terminator()) {
                 Ok(ExprKind::Return(None).with_span(tok.span))
             } else {
                 let expr = stream.parse()?;
-                let span = tok.span.start()..((&stream.ast.exprs[expr] as &Expr).span.end());
+                let span = tok.span.to((&stream.ast.exprs[expr] as &Expr).span);
                 Ok(ExprKind::Return(Some(expr)).with_span(span))
             }
         }
         TokenKind::Impl => Ok(ExprKind::Impl(stream.parse()?).todo_span()),
         TokenKind::Trait => Ok(ExprKind::Trait(stream.parse()?).todo_span()),
         TokenKind::Fn => Ok(ExprKind::FnDecl(stream.parse()?).todo_span()),
+        TokenKind::Pipe => {
+            let params = stream.parse_separated(TokenKind::Comma, TokenKind::Pipe)?;
+            let body: ExprId = stream.parse()?;
+            let span = tok.span.to(stream.ast.exprs[body].span);
+            Ok(ExprKind::Closure { params, body }.with_span(span))
+        }
         TokenKind::Struct => parse_struct(stream),
         TokenKind::Const | TokenKind::Let => parse_var(stream, tok),
-        TokenKind::While => parse_while(stream),
-        TokenKind::For => parse_for(stream),
+        TokenKind::While | TokenKind::Loop | TokenKind::For => parse_loop(stream, tok, None),
+        TokenKind::Label => {
+            let label =
+                Identifier { symbol: Symbol::from(&stream.lexer.src()[tok.span]), span: tok.span };
+            stream.expect(TokenKind::Colon)?;
+            let loop_tok = stream.any(&[TokenKind::Loop, TokenKind::While, TokenKind::For])?;
+            parse_loop(stream, loop_tok, Some(label))
+        }
         TokenKind::Match => parse_match(stream, tok),
         TokenKind::If => parse_ifchain(stream, tok),
         TokenKind::True => lit!(Lit::Bool(true)),
         TokenKind::False => lit!(Lit::Bool(false)),
         TokenKind::Int => lit!(Lit::Int(stream.lexer.src()[tok.span].parse::<i64>().unwrap())),
-        TokenKind::Str => parse_string(stream, tok.span),
-        TokenKind::Char => {
-            // TODO: Escaping
-            let str = &stream.lexer.src()[tok.span.shrink(1)];
-            lit!(Lit::Char(str.chars().next().unwrap()))
-        }
+        TokenKind::Str => parse_string(stream, tok.span, false),
+        TokenKind::RawStr => parse_string(stream, tok.span, true),
+        TokenKind::Char => lit!(Lit::Char(parse_char(stream, tok.span)?)),
         TokenKind::Ident => {
             Ok(ExprKind::Ident(stream.lexer.src()[tok.span].into()).with_span(tok.span))
         }
@@ -584,80 +827,97 @@ fn parse_atom_with(stream: &mut Stream, tok: Token) -> Result<ExprId> {
                 &format!("expected `expression`, found {found:?}"),
                 stream.path,
                 stream.lexer.src(),
-                [(stream.lexer.span(), "here")],
+                [(tok.span, "here")],
             ));
         }
     };
     Ok(stream.ast.exprs.push(expr?))
 }
 
-fn parse_string(stream: &mut Stream, outer_span: Span) -> Result<Expr> {
-    // FIXME: Bring a cross.
-    let span = outer_span.shrink(1); // remove double quotes.
-    let raw = &stream.lexer.src()[span];
-    let lexer_offset = stream.lexer.offset();
-    stream.lexer.set_offset(span.start() as usize);
-    let mut current_start = span.start() as usize;
+// raw strings take backslashes literally, but still interpolate `${...}`
+fn parse_string(stream: &mut Stream, outer_span: Span, raw: bool) -> Result<Expr> {
+    // remove the quotes and the `r` prefix of raw strings
+    let span = Span::from(outer_span.start() + u32::from(raw) + 1..outer_span.end() - 1);
+    let offset = span.start() as usize;
+    let mut chars =
+        stream.lexer.src()[span].char_indices().map(|(i, c)| (offset + i, c)).peekable();
+    let mut current_start = offset;
     let mut current = String::new();
     let mut segments = thin_vec![];
 
-    let mut chars = raw.char_indices();
-
-    let mut escaped = false;
-    while let Some((char_pos, char)) = chars.next() {
+    while let Some((pos, char)) = chars.next() {
         match char {
-            '$' if !escaped && chars.clone().next().is_some_and(|c| c.1 == '{') => {
-                let char_pos = chars.next().unwrap().0 + span.start() as usize;
+            '$' if chars.next_if(|&(_, c)| c == '{').is_some() => {
                 if !current.is_empty() {
-                    let current_span = Span::from(current_start..char_pos);
-                    let expr =
-                        ExprKind::Lit(Lit::Str(current.as_str().into())).with_span(current_span);
-                    segments.push(stream.ast.exprs.push(expr));
+                    let expr = ExprKind::Lit(Lit::Str(current.as_str().into()));
+                    segments.push(stream.ast.exprs.push(expr.with_span(current_start..pos)));
                     current.clear();
                 }
-
-                stream.lexer.bump(char_pos - current_start + 1);
-                let offset = stream.lexer.offset();
+                // the lexer has already matched the braces, parse what's between them in place
+                stream.seek(pos + 2);
                 segments.push(stream.parse()?);
-                let diff = stream.lexer.offset() - offset;
-
-                chars = chars.as_str()[diff..].char_indices();
-                let next = chars.next().unwrap();
-                assert_eq!(next.1, '}');
-                current_start = next.0 + span.start() as usize;
+                stream.expect(TokenKind::RBrace)?;
+                current_start = stream.pos() as usize;
+                while chars.next_if(|&(i, _)| i < current_start).is_some() {}
             }
-            '\\' if !escaped => escaped = true,
-            _ if !escaped => current.push(char),
-            _ => {
-                escaped = false;
-                match char {
+            '\\' if !raw => {
+                let Some((escape_pos, escaped)) = chars.next() else { break };
+                match escaped {
                     '\\' => current.push('\\'),
                     'n' => current.push('\n'),
                     '$' => current.push('$'),
+                    // a trailing `\` continues the string on the next line without the line break
+                    '\n' | '\r' => while chars.next_if(|&(_, c)| c.is_whitespace()).is_some() {},
                     _ => {
-                        let span = Span::new(
-                            current_start..span.start() as usize + char_pos + char.len_utf8(),
-                            span.source(),
-                        );
-                        return Err(invalid_escape(stream, span, char));
+                        let span = pos..escape_pos + escaped.len_utf8();
+                        return Err(invalid_escape(stream, Span::from(span), escaped));
                     }
                 }
             }
+            _ => current.push(char),
         }
     }
     if segments.is_empty() {
-        stream.lexer.set_offset(lexer_offset);
         return Ok(ExprKind::Lit(Lit::Str(current.into())).with_span(outer_span));
     }
     if !current.is_empty() {
-        let current_span = Span::from(current_start..(current_start + raw.len()));
+        let current_span = current_start..span.end() as usize;
         let expr = ExprKind::Lit(Lit::Str(current.into())).with_span(current_span);
         segments.push(stream.ast.exprs.push(expr));
     }
-    stream.lexer.set_offset(lexer_offset);
+    stream.seek(outer_span.end() as usize);
     Ok(ExprKind::Lit(Lit::FStr(segments)).with_span(outer_span))
 }
 
+fn parse_char(stream: &mut Stream, span: Span) -> Result<char> {
+    let src = &stream.lexer.src()[span];
+    let error = |msg: &str, label: &str| {
+        errors::error(msg, stream.path, stream.lexer.src(), [(span, label)])
+    };
+    let Some(inner) = src.strip_prefix('\'').and_then(|src| src.strip_suffix('\'')) else {
+        return Err(error("unterminated character literal", "missing closing `'`"));
+    };
+    let mut chars = inner.chars();
+    let char = match chars.next() {
+        None => return Err(error("empty character literal", "expected a character")),
+        Some('\\') => match chars.next() {
+            Some('\\') => '\\',
+            Some('n') => '\n',
+            Some('\'') => '\'',
+            Some(char) => return Err(invalid_escape(stream, span, char)),
+            None => return Err(error("unterminated character literal", "missing closing `'`")),
+        },
+        Some(char) => char,
+    };
+    if chars.next().is_some() {
+        return Err(error(
+            "character literal may only contain one character",
+            "more than one character",
+        ));
+    }
+    Ok(char)
+}
+
 fn invalid_escape(stream: &mut Stream<'_, '_>, span: Span, char: char) -> Error {
     errors::error(
         &format!("invalid escape character {char:?}"),
@@ -667,6 +927,19 @@ fn invalid_escape(stream: &mut Stream<'_, '_>, span: Span, char: char) -> Error
     )
 }
 
+impl Parse for Generic {
+    fn parse(stream: &mut Stream) -> Result<Self> {
+        let ident = stream.parse()?;
+        let bound = if stream.peek()?.kind == TokenKind::Colon {
+            _ = stream.next();
+            Some(stream.parse()?)
+        } else {
+            None
+        };
+        Ok(Self { ident, bound })
+    }
+}
+
 impl Parse for Identifier {
     fn parse(stream: &mut Stream) -> Result<Self> {
         let span = stream.expect(TokenKind::Ident)?.span;
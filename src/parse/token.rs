@@ -13,6 +13,7 @@ pub enum TokenKind {
     ThinArrow,
     FatArrow,
     Ampersand,
+    Pipe,
 
     LBrace,
     RBrace,
@@ -58,17 +59,21 @@ pub enum TokenKind {
     Struct,
     True,
     While,
+    Loop,
     For,
     In,
     Match,
     Unreachable,
     Trait,
     Impl,
+    Mut,
     // Literals
     Char,
     Int,
     Str,
+    RawStr,
     Ident,
+    Label,
 
     Unknown,
 }
@@ -86,7 +91,9 @@ impl TokenKind {
             Self::Or => "or",
             Self::Trait => "trait",
             Self::Impl => "impl",
+            Self::Mut => "mut",
             Self::Ampersand => "&",
+            Self::Pipe => "|",
             Self::Unreachable => "unreachable",
             Self::Assert => "assert",
             Self::Break => "break",
@@ -94,6 +101,7 @@ impl TokenKind {
             Self::Let => "let",
             Self::Const => "const",
             Self::While => "while",
+            Self::Loop => "loop",
             Self::For => "for",
             Self::In => "in",
             Self::True => "true",
@@ -114,6 +122,7 @@ impl TokenKind {
             Self::Greater => ">",
             Self::GreaterEq => ">=",
             Self::Ident => "identifier",
+            Self::Label => "label",
             Self::Int => "integer",
             Self::LBrace => "{",
             Self::LBracket => "[",
@@ -136,6 +145,7 @@ impl TokenKind {
             Self::Slash => "/",
             Self::Star => "*",
             Self::Str => "string",
+            Self::RawStr => "raw string",
             Self::ThinArrow => "->",
             Self::FatArrow => "=>",
             Self::Struct => "struct",
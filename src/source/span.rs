@@ -13,14 +13,15 @@ pub struct Span {
 }
 
 impl Span {
+    /// The smallest span containing every span, or `Span::ZERO` if there are none.
     pub fn join(spans: impl IntoIterator<Item = Self>) -> Self {
-        let mut start = u32::MAX;
-        let mut end = 0;
-        spans.into_iter().for_each(|span| {
-            start = start.min(span.start());
-            end = start.max(span.end());
-        });
-        Self::from(start..end)
+        spans.into_iter().reduce(Self::to).unwrap_or(Self::ZERO)
+    }
+    /// The smallest span containing both `self` and `other`, including any gap between them.
+    pub fn to(self, other: Self) -> Self {
+        let start = self.start.min(other.start);
+        let end = self.end().max(other.end());
+        Self::new(start as usize..end as usize, self.source)
     }
     pub fn source(self) -> SourceId {
         self.source
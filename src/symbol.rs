@@ -1,31 +1,85 @@
-use std::{fmt, ops::Deref};
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    ops::Deref,
+    sync::{
+        Mutex,
+        atomic::{self, AtomicBool},
+    },
+};
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+use rustc_hash::{FxBuildHasher, FxHashSet};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Symbol {
     inner: symbol_table::GlobalSymbol,
 }
 
+// the global table cannot be enumerated, so every distinct symbol is also recorded here,
+// but only once `record_interned` has been called since it is only needed for stats
+static INTERNED: Mutex<FxHashSet<Symbol>> = Mutex::new(HashSet::with_hasher(FxBuildHasher));
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
 impl Symbol {
     pub fn as_str(self) -> &'static str {
         self.inner.as_str()
     }
+    fn new(inner: symbol_table::GlobalSymbol) -> Self {
+        let symbol = Self { inner };
+        if RECORDING.load(atomic::Ordering::Relaxed) {
+            INTERNED.lock().unwrap().insert(symbol);
+        }
+        symbol
+    }
+}
+
+/// Starts recording symbols for `interned_count` and `interned`.
+pub fn record_interned() {
+    RECORDING.store(true, atomic::Ordering::Relaxed);
+}
+
+/// The number of distinct symbols interned since recording started.
+pub fn interned_count() -> usize {
+    INTERNED.lock().unwrap().len()
+}
+
+/// Every distinct symbol interned since recording started, sorted by their contents.
+pub fn interned() -> Vec<Symbol> {
+    let mut symbols: Vec<_> = INTERNED.lock().unwrap().iter().copied().collect();
+    symbols.sort_unstable();
+    symbols
 }
 
 impl From<&str> for Symbol {
     fn from(value: &str) -> Self {
-        Self { inner: value.into() }
+        Self::new(value.into())
     }
 }
 
 impl From<String> for Symbol {
     fn from(value: String) -> Self {
-        Self { inner: value.into() }
+        Self::new(value.into())
     }
 }
 
 impl From<&String> for Symbol {
     fn from(value: &String) -> Self {
-        Self { inner: value.into() }
+        Self::new(value.into())
+    }
+}
+
+// symbols are ordered by their contents rather than the order they were interned in,
+// so anything sorted by symbol is the same between runs
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other { Ordering::Equal } else { self.as_str().cmp(other.as_str()) }
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
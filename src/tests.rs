@@ -1,4 +1,24 @@
-use crate::compile::compile_test;
+use std::{fs, ptr};
+
+use petty_intern::Interner;
+use thin_vec::thin_vec;
+
+use crate::{
+    ast::{Generic, Identifier, Mutability},
+    compile::{
+        Source, compile, compile_hir_test, compile_mir_test, compile_test, hir_from_src,
+        hir_snapshot, mir_from_src, mir_snapshot, test_args,
+    },
+    hir,
+    mir::{
+        BinaryOp, Block, BlockId, Body, BodyId, Constant, Local, Mir, Operand, Place, Projection,
+        RValue, Statement, Terminator, UnaryOp,
+    },
+    mir_interpreter::{self, Allocation, RuntimeError, Step, Value},
+    mir_optimizations::utils::{blocks, blocks_mut},
+    span::Span,
+    ty::{Function, GenericRange, Ty, TyCtx, TyKind},
+};
 
 macro_rules! test {
     {$name: ident} => {
@@ -28,18 +48,895 @@ test! {
     if_expr
     functions
     structs
+    struct_fields
     ref_assignment
     precedence
     chars
+    char_ranges
     strings
     format
     recursion
     refs
     variables
     logical
+    field_liveness
+    params
+    generic_structs
+    traits
+    literal_defaulting
+    builtin_methods
+    aliasing_assign
+    evaluation_order
+    str_ordering
+    str_len
+    concat
+    int_radix
+    struct_params
+    forward_refs
+    recursive_structs
+    generic_nested_fns
+    mutual_recursion
+    unreachable
+    generic_divergence
+    assert_unit
+    assoc_consts
+    operator_overloading
+    index_overloading
+    struct_ordering
+    if_let
+    while_let
+    loop_labels
+    loop_scopes
+    temp_refs
+    deref_depth
+    deref_assign
+    field_refs
+    equality
+    maps
+    sets
+    sort
+    reverse_contains
+    higher_order
+    closures
+    fn_values
+    tuples
+    destructuring
+    loops
+    trailing_commas
+    interpolation
+    raw_strings
+    multiline_strings
+    never_coercion
     // should panic
     "expected `!`, found `int`" fail_never
     "expected `int`, found `str`" fail_variables
     "expected `int`, found `str`" fail_return
     "assertion failed" fail_assert
+    "the trait `Display` is not implemented for `bool`" fail_trait_bound
+    "method `describe` has an incompatible type for `Describe`" fail_trait_signature
+    "cannot mutate through a shared reference `&int`" fail_shared_ref
+    "cannot mutate through a shared reference `&Point`" fail_shared_field
+    "cannot compare values of type `[Map<int, int>]`" fail_equality
+    "the trait `Hash` is not implemented for `bool`" fail_map_key
+    "the trait `Ord` is not implemented for `bool`" fail_sort
+    "cannot compare values of type `Map<int, int>`" fail_contains
+    "cannot capture local variable 'y'" fail_capture
+    "expected `(_, _)`, found `(int, int, int)`" fail_destructure
+    "expected `int`, found `()`" fail_loop_break
+    "expected `int`, found `()`" fail_block_value
+    "expected `expression`, found Comma" fail_lone_comma
+    "expected `}`, found: `integer`" fail_interpolation
+    "empty character literal" fail_empty_char
+    "character literal may only contain one character" fail_multi_char
+    "`return` outside of a function" fail_toplevel_return
+    "`break` outside of a loop" fail_break_outside_loop
+    "expected `int`, found `()`" fail_missing_return
+    "expected `int`, found `()`" fail_bare_return
+    "cannot infer type _" fail_infer_closure
+    "expected 1 arguments, found 2" fail_shadowed_print
+    "expected 1 arguments, found 2" fail_shadowed_concat
+    "cannot assign to an overloaded index" fail_index_assign
+    "method `index` has an incompatible type for `Index`" fail_index_signature
+    "cannot compare values of type `Flag`" fail_struct_ordering
+    "expected `int`, found `char`" fail_int_char_eq
+    "cannot compare values of type `char` with `int`" fail_char_int_cmp
+    "no field `x` on type `int`" fail_field_on_int
+    "no field `2` on type `(int, str)`" fail_tuple_index
+    "expected `bool`, found `(int, int)`" fail_const_tuple
+    "expected `str`, found `int`" fail_concat
+    "string index out of bounds" fail_str_index_negative
+    "string index out of bounds" fail_str_index_past_end
+    "index out of bounds" fail_array_index_negative
+    "key not found" fail_map_missing_key
+    "use of undeclared label `'inner`" fail_undeclared_label
+    "cannot find 'inside' in this scope" fail_loop_scope
+}
+
+#[test]
+fn const_branch() {
+    let mir = compile_mir_test("tests/const_branch.pty").unwrap();
+    let main = &mir.bodies[mir.main_body.unwrap()];
+    assert_eq!(main.blocks.len(), 1, "{}", mir.display(false));
+}
+
+#[test]
+fn intrinsics() {
+    let output = compile_test("tests/intrinsics.pty").unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "ab\nc");
+
+    // an intrinsic that wasn't routed would still be lowered from its `unreachable` body
+    let mir = compile_mir_test("tests/intrinsics.pty").unwrap();
+    for body in mir.bodies.iter().filter(|body| body.auto) {
+        for block in &body.blocks {
+            assert!(!matches!(block.terminator, Terminator::Unreachable), "{}", mir.display(true));
+        }
+    }
+}
+
+#[test]
+fn dead_print() {
+    let output = compile_test("tests/dead_print.pty").unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "1\nab\n");
+}
+
+#[test]
+fn shadowed_print() {
+    let output = compile_test("tests/shadowed_print.pty").unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "shadowed");
+}
+
+#[test]
+fn missing_comma() {
+    let errors = compile_test("tests/missing_comma.pty").unwrap_err();
+    let label = errors[0].labels().unwrap().next().unwrap();
+    // the label sits right after `1`, where the `,` was expected
+    let src = fs::read_to_string("tests/missing_comma.pty").unwrap();
+    assert_eq!(label.offset(), src.find("1 2").unwrap() + 1);
+    assert_eq!(label.len(), 0);
+}
+
+#[test]
+fn unknown_char() {
+    let errors = compile_test("tests/unknown_char.pty").unwrap_err();
+    assert_eq!(errors[0].to_string(), "unknown character `@`");
+    let label = errors[0].labels().unwrap().next().unwrap();
+    let src = fs::read_to_string("tests/unknown_char.pty").unwrap();
+    assert_eq!((label.offset(), label.len()), (src.find('@').unwrap(), 1));
+}
+
+#[test]
+fn lexer_positions() {
+    use crate::parse::Lexer;
+
+    // `é` lexes as a single unknown token two bytes wide
+    let src = "let é = 1; // c\n  x  ";
+    let spans = Lexer::new(src).map(|token| token.span.into_range()).collect::<Vec<_>>();
+    assert_eq!(spans, [0..3, 4..6, 7..8, 9..10, 10..11, 19..20]);
+
+    let mut lexer = Lexer::new(src);
+    assert_eq!(lexer.current_pos(), 0);
+    lexer.nth(1);
+    assert_eq!(lexer.current_pos(), 6);
+    lexer.set_offset(9);
+    assert_eq!(lexer.next().unwrap().span.into_range(), 9..10);
+    assert_eq!(lexer.current_pos(), 10);
+    lexer.set_offset(19);
+    lexer.next();
+    assert_eq!(lexer.span_eof().into_range(), 20..22);
+    assert!(lexer.next().is_none());
+    assert_eq!(lexer.current_pos(), 22);
+}
+
+#[test]
+fn lookahead_lexes_once() {
+    use crate::parse::{Lexer, TOKENS_LEXED, parse};
+
+    let src = fs::read_to_string("tests/trailing_commas.pty").unwrap().replace("main", "_main");
+    let src = src.repeat(200);
+    let tokens = Lexer::new(&src).count();
+    TOKENS_LEXED.set(0);
+    parse(&src, None).unwrap();
+    // peeking reuses the buffered token, only speculative forks lex anything twice
+    let lexed = TOKENS_LEXED.get();
+    assert!(lexed < tokens * 11 / 10, "lexed {lexed} tokens for {tokens} in the source");
+}
+
+#[test]
+fn symbols_interned_once() {
+    use crate::symbol::{self, Symbol};
+
+    // other tests intern concurrently, so only look at this test's own symbol
+    let occurrences =
+        || symbol::interned().iter().filter(|&&s| s == "symbols_interned_once").count();
+    symbol::record_interned();
+    _ = Symbol::from("symbols_interned_once");
+    assert_eq!(occurrences(), 1);
+    _ = Symbol::from(String::from("symbols_interned_once"));
+    assert_eq!(occurrences(), 1);
+    assert!(symbol::interned_count() >= 1);
+}
+
+#[test]
+fn symbols_sort_by_contents() {
+    use crate::symbol::Symbol;
+
+    // interned in reverse, so their ids are ordered the other way around
+    let mut symbols: Vec<_> =
+        ["symbol_sort_c", "symbol_sort_b", "symbol_sort_a"].map(Symbol::from).into();
+    symbols.sort();
+    assert_eq!(symbols, ["symbol_sort_a", "symbol_sort_b", "symbol_sort_c"]);
+    assert!(Symbol::from("symbol_sort_ab") < Symbol::from("symbol_sort_b"));
+}
+
+#[test]
+fn parse_errors() {
+    // parsing resumes at the next item, so both syntax errors are reported together
+    let errors = compile_test("tests/parse_errors.pty").unwrap_err();
+    let messages = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+    assert_eq!(
+        messages,
+        [
+            "expected `expression`, found Semicolon",
+            "expected one of `,` or `)`, found `identifier`"
+        ]
+    );
+}
+
+#[test]
+fn nested_return() {
+    compile_test("tests/nested_return.pty").unwrap();
+
+    // every jump left behind by an early `return` must have been completed
+    let mir = compile_mir_test("tests/nested_return.pty").unwrap();
+    for body in &mir.bodies {
+        for block in &body.blocks {
+            let targets = match block.terminator {
+                Terminator::Goto(target) => vec![target],
+                Terminator::Branch { fals, tru, .. } => vec![fals, tru],
+                _ => vec![],
+            };
+            let in_bounds = targets.iter().all(|target| target.index() < body.blocks.len());
+            assert!(in_bounds, "{}", mir.display(true));
+        }
+    }
+}
+
+#[test]
+fn never_tail() {
+    compile_test("tests/never_tail.pty").unwrap();
+
+    // a block ending in `return` or `break` must not be followed by a synthesized `()`
+    compile_hir_test("tests/never_tail.pty", |hir| {
+        let bodies = hir.exprs.iter().flat_map(|expr| match &expr.kind {
+            hir::ExprKind::Block(body) | hir::ExprKind::Loop { body, .. } => vec![body],
+            hir::ExprKind::FnDecl(decl) => vec![&decl.body],
+            hir::ExprKind::If { arms, els } => {
+                arms.iter().map(|arm| &arm.body).chain([els]).collect()
+            }
+            _ => vec![],
+        });
+        for body in bodies {
+            let [.., before, last] = body[..] else { continue };
+            let is_unit = matches!(hir.exprs[last].kind, hir::ExprKind::Literal(hir::Lit::Unit));
+            assert!(!(hir.exprs[before].ty == Ty::NEVER && is_unit), "{body:?}");
+        }
+    })
+    .unwrap();
+}
+
+// every program under `tests` that is expected to compile
+fn passing_tests() -> impl Iterator<Item = std::path::PathBuf> {
+    let error_tests = ["missing_comma", "unknown_char", "parse_errors"];
+    fs::read_dir("tests").unwrap().map(|entry| entry.unwrap().path()).filter(move |path| {
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        path.extension().is_some_and(|ext| ext == "pty")
+            && !name.starts_with("fail_")
+            && !error_tests.contains(&name)
+    })
+}
+
+#[test]
+fn full_inference() {
+    // every program that passes analysis must leave no inference variables behind
+    for path in passing_tests() {
+        compile_hir_test(&path, |hir| {
+            for expr in &hir.exprs {
+                assert!(!expr.ty.has_infer(), "{}: {:?}", path.display(), expr.kind);
+            }
+        })
+        .unwrap_or_else(|errors| panic!("{}: {errors:?}", path.display()));
+    }
+}
+
+#[test]
+fn deterministic_mir() {
+    // the second compile sees symbols interned by the first and types at other addresses
+    for path in passing_tests() {
+        let first = compile_mir_test(&path).unwrap().to_string();
+        let second = compile_mir_test(&path).unwrap().to_string();
+        assert!(first == second, "{} compiled to different mir", path.display());
+    }
+}
+
+#[test]
+fn hashmap_iteration_order() {
+    let build = || (0..1000).map(|i| (i * 7919 % 1009, i)).collect::<crate::HashMap<_, _>>();
+    let first: Vec<_> = build().into_iter().collect();
+    let second: Vec<_> = build().into_iter().collect();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn block_values() {
+    let output = compile_test("tests/block_values.pty").unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "5\n()\n5\n()\n5\n()\n1\n");
+}
+
+#[test]
+fn print_newlines() {
+    let output = compile_test("tests/print.pty").unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "abc\n\nd");
+}
+
+#[test]
+fn print_any() {
+    let output = compile_test("tests/print_any.pty").unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "42\ntrue\nPoint { x: 1, y: 2 }\n[1, 2]c(1, a)\n7\n"
+    );
+
+    // direct calls format in place, only the indirect call needs an instance of `println`
+    let mir = compile_mir_test("tests/print_any.pty").unwrap();
+    let printlns = mir.bodies.iter().filter(|body| {
+        body.name.is_some_and(|name| *name == *"println") && !body.blocks.is_empty()
+    });
+    assert_eq!(printlns.count(), 1, "{}", mir.display(true));
+}
+
+#[test]
+fn print_many() {
+    let output = compile_test("tests/print_many.pty").unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "1 x true\nPoint { x: 1, y: 2 } [3] c\na b");
+}
+
+#[test]
+fn with_locals() {
+    fn locals(f: impl FnOnce(&mut dyn FnMut(Local))) -> Vec<usize> {
+        let mut out = vec![];
+        f(&mut |local| out.push(local.index()));
+        out
+    }
+    let place = |local: usize, projections| Place { local: local.into(), projections };
+    let indexed =
+        place(0, vec![Projection::Deref, Projection::Index(1.into()), Projection::Field(2)]);
+
+    assert_eq!(locals(|f| indexed.with_locals(f)), [0, 1]);
+    assert_eq!(locals(|f| Projection::Index(3.into()).with_locals(f)), [3]);
+    assert!(locals(|f| Projection::ConstantIndex(3).with_locals(f)).is_empty());
+    assert!(locals(|f| Operand::Constant(Constant::Int(1)).with_locals(f)).is_empty());
+    assert_eq!(locals(|f| Operand::Ref(indexed.clone()).with_locals(f)), [0, 1]);
+
+    let rvalue = RValue::Binary {
+        lhs: Operand::Place(indexed),
+        op: BinaryOp::IntAdd,
+        rhs: Operand::local(2.into()),
+    };
+    assert_eq!(locals(|f| rvalue.with_locals(f)), [0, 1, 2]);
+    let rvalue = RValue::Unary { op: UnaryOp::IntNeg, operand: Operand::local(4.into()) };
+    assert_eq!(locals(|f| rvalue.with_locals(f)), [4]);
+    let rvalue = RValue::Call {
+        function: Operand::local(5.into()),
+        args: thin_vec![Operand::local(6.into()), Operand::UNIT],
+    };
+    assert_eq!(locals(|f| rvalue.with_locals(f)), [5, 6]);
+    let rvalue = RValue::BuildArray(vec![
+        (Operand::local(7.into()), None),
+        (Operand::local(8.into()), Some(Operand::local(9.into()))),
+    ]);
+    assert_eq!(locals(|f| rvalue.with_locals(f)), [7, 8, 9]);
+    let rvalue = RValue::StrJoin(vec![Operand::local(10.into()), Operand::local(11.into())]);
+    assert_eq!(locals(|f| rvalue.with_locals(f)), [10, 11]);
+
+    let terminator =
+        Terminator::Branch { condition: Operand::local(12.into()), fals: 0.into(), tru: 1.into() };
+    assert_eq!(locals(|f| terminator.with_locals(f)), [12]);
+    assert_eq!(locals(|f| Terminator::Return(Operand::local(13.into())).with_locals(f)), [13]);
+    assert!(locals(|f| Terminator::Goto(0.into()).with_locals(f)).is_empty());
+}
+
+#[test]
+fn block_iter_order() {
+    let mut body = Body::new(None, 0);
+    for terminator in [
+        Terminator::Goto(2.into()),
+        Terminator::Return(Operand::UNIT),
+        Terminator::Goto(1.into()),
+        Terminator::Unreachable,
+    ] {
+        body.blocks.push(Block { statements: vec![], terminator });
+    }
+    let position =
+        |block: &Block| body.blocks.iter().position(|other| ptr::eq(other, block)).unwrap();
+    assert_eq!(blocks(&body).into_iter().map(position).collect::<Vec<_>>(), [0, 1, 2]);
+
+    for (i, block) in blocks_mut(&mut body).into_iter().enumerate() {
+        block.terminator = Terminator::Goto(i.into());
+    }
+    let jumps = body.blocks.iter().map(|block| match block.terminator {
+        Terminator::Goto(id) => Some(id.index()),
+        _ => None,
+    });
+    assert_eq!(jumps.collect::<Vec<_>>(), [Some(0), Some(1), Some(2), None]);
+}
+
+#[test]
+fn params_stay_alive() {
+    let mir = compile_mir_test("tests/params.pty").unwrap();
+    for name in ["second", "overwritten"] {
+        let body = mir.bodies.iter().find(|body| body.name.is_some_and(|n| *n == *name)).unwrap();
+        assert_eq!(body.params, 2);
+        assert!(body.locals.index() >= body.params, "{}", mir.display(false));
+    }
+}
+
+#[test]
+fn formatters_are_auto() {
+    let mir = compile_mir_test("tests/format.pty").unwrap();
+    for formatter in ["format_struct", "format_array"] {
+        let mut bodies =
+            mir.bodies.iter().filter(|body| body.name.is_some_and(|n| *n == *formatter));
+        assert!(bodies.clone().next().is_some());
+        assert!(bodies.all(|body| body.auto));
+    }
+    assert!(!mir.bodies[mir.main_body.unwrap()].auto);
+}
+
+#[test]
+fn monomorphization() {
+    let mir = compile_mir_test("tests/generics.pty").unwrap();
+    let instances = mir
+        .bodies
+        .iter()
+        .filter(|body| body.name.is_some_and(|name| *name == *"id") && !body.blocks.is_empty());
+    // one body each for `int` and `str`, the generic declaration itself is never lowered
+    assert_eq!(instances.count(), 2, "{}", mir.display(true));
+}
+
+#[test]
+fn display_ty() {
+    let interner = Interner::default();
+    let tcx = TyCtx::new(&interner);
+    let display = |ty| tcx.display(ty).to_string();
+
+    let simple = [
+        Ty::NEVER,
+        Ty::UNIT,
+        Ty::BOOL,
+        Ty::INT,
+        Ty::CHAR,
+        Ty::STR,
+        Ty::RANGE,
+        Ty::CHAR_RANGE,
+        Ty::POISON,
+    ];
+    let simple = simple.map(display);
+    assert_eq!(
+        simple,
+        ["!", "()", "bool", "int", "char", "str", "Range", "Range<char>", "<poisoned>"]
+    );
+
+    assert_eq!(display(tcx.intern(TyKind::Array(Ty::INT))), "[int]");
+    assert_eq!(display(tcx.intern(TyKind::Ref(Ty::STR, Mutability::Not))), "&str");
+    assert_eq!(display(tcx.intern(TyKind::Tuple([Ty::INT, Ty::STR].into()))), "(int, str)");
+    let func = Function { params: [Ty::INT, Ty::BOOL].into(), ret: Ty::STR };
+    assert_eq!(display(tcx.intern(TyKind::Function(func))), "fn(int, bool) -> str");
+    assert_eq!(display(tcx.new_infer()), "_");
+
+    let generic = |name: &str| Generic {
+        ident: Identifier { symbol: name.into(), span: Span::ZERO },
+        bound: None,
+    };
+    let generics = tcx.new_generics(&[generic("A"), generic("B")]);
+    let [a, b] = [0, 1].map(|i| tcx.intern(TyKind::Generic(generics.start + i)));
+    assert_eq!(display(a), "A");
+
+    let point = tcx.new_struct("Point".into(), GenericRange::EMPTY, ["x".into()].into());
+    assert_eq!(display(point), "Point");
+    let pair = tcx.new_struct("Pair".into(), generics, ["a".into(), "b".into()].into());
+    let TyKind::Struct { id, .. } = *pair.0 else { unreachable!() };
+    tcx.set_struct_fields(id, &[a, tcx.intern(TyKind::Array(b))]);
+    assert_eq!(display(pair), "Pair<A, B>");
+    let pair =
+        pair.replace_generics(&tcx, |id| if id == generics.start { Ty::INT } else { Ty::STR });
+    assert_eq!(display(pair), "Pair<int, str>");
+}
+
+#[test]
+fn span_merging() {
+    let overlapping = Span::from(2..6u32).to(Span::from(4..9u32));
+    assert_eq!(overlapping, Span::from(2..9u32));
+
+    let (a, b) = (Span::from(0..2u32), Span::from(5..7u32));
+    assert_eq!(a.to(b), Span::from(0..7u32));
+    assert_eq!(b.to(a), Span::from(0..7u32));
+
+    assert_eq!(Span::join([b, Span::from(3..4u32), a]), Span::from(0..7u32));
+}
+
+#[test]
+fn mismatch_labels() {
+    let errors = compile_test("tests/fail_let_annotation.pty").unwrap_err();
+    let mut out = String::new();
+    miette::GraphicalReportHandler::new_themed(miette::GraphicalTheme::unicode_nocolor())
+        .render_report(&mut out, errors[0].as_ref())
+        .unwrap();
+    assert!(out.contains("expected `int`, found `str`"), "{out}");
+    assert!(out.contains("expected due to this"), "{out}");
+}
+
+#[test]
+fn warnings_do_not_abort() {
+    let path = std::path::Path::new("tests/warnings.pty");
+    let source = Source::read(path).unwrap();
+    let ty_intern = Interner::default();
+    let tcx = TyCtx::new(&ty_intern);
+    let ast = source.parse().unwrap();
+    let analysis = source.analyze(&ast, &tcx).unwrap();
+    assert_eq!(analysis.warnings.len(), 1);
+    assert_eq!(analysis.warnings[0].severity(), Some(miette::Severity::Warning));
+    assert_eq!(analysis.warnings[0].to_string(), "unused variable: `unused`");
+
+    assert_eq!(compile_test(path).unwrap(), b"still runs\n");
+}
+
+#[test]
+fn deny_warnings() {
+    let mut args = test_args("tests/warnings.pty", true);
+    let compiled = compile(&args, &mut vec![]).unwrap();
+    compiled.code.unwrap();
+    // warnings are left for the caller to render
+    assert_eq!(compiled.warnings[0].to_string(), "unused variable: `unused`");
+
+    args.deny_warnings = true;
+    let mut out = vec![];
+    let errors = compile(&args, &mut out).unwrap_err();
+    assert_eq!(errors[0].to_string(), "unused variable: `unused`");
+    assert!(out.is_empty());
+}
+
+#[test]
+fn struct_sizes() {
+    compile_test("tests/struct_sizes.pty").unwrap();
+
+    // each constructor allocates exactly one slot per field
+    let mir = compile_mir_test("tests/struct_sizes.pty").unwrap();
+    for (name, fields) in [("Empty", 0u32), ("Five", 5)] {
+        let body = mir.bodies.iter().find(|body| body.name == Some(name.into())).unwrap();
+        assert_eq!(body.params, fields as usize);
+        let constants = blocks(body).into_iter().flat_map(|block| {
+            let returned = match &block.terminator {
+                Terminator::Return(Operand::Constant(constant)) => Some(constant),
+                _ => None,
+            };
+            let assigned = block.statements.iter().filter_map(|stmt| match stmt {
+                Statement::Assign { rvalue: RValue::Use(Operand::Constant(constant)), .. } => {
+                    Some(constant)
+                }
+                Statement::Assign { .. } => None,
+            });
+            assigned.chain(returned)
+        });
+        let sizes: Vec<_> = constants
+            .filter_map(|constant| match *constant {
+                Constant::UninitStruct { size } => Some(size),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sizes, [fields], "{}", mir.display(true));
+    }
+}
+
+#[test]
+fn clone_cyclic_value() {
+    // a struct with a field referring back to the struct itself
+    let outer = Allocation::from(Value::Struct(thin_vec![Allocation::from(Value::Int(1))]));
+    if let Value::Struct(fields) = &mut *outer.borrow() {
+        fields.push(Allocation::from(Value::Ref(outer.clone())));
+    }
+    assert_eq!(outer.count(), 2);
+
+    let copy = outer.clone_raw();
+    // the copy shares the original allocation instead of copying it again
+    assert_eq!(outer.count(), 3);
+    let Value::Struct(fields) = &copy else { unreachable!() };
+    assert!(matches!(*fields[1].borrow(), Value::Ref(ref inner) if inner.count() == 3));
+}
+
+#[test]
+fn compile_stages() {
+    let src = "fn double(x: int) -> int { x * 2 }\nfn main() { println(double(21)); }";
+    hir_from_src(src, |hir, _| {
+        let double = hir.root.iter().find_map(|&id| match &hir.exprs[id].kind {
+            hir::ExprKind::FnDecl(decl) if decl.ident.as_str() == "double" => Some(decl),
+            _ => None,
+        });
+        let double = double.unwrap();
+        assert_eq!(double.params.len(), 1);
+        assert_eq!(double.ret, Ty::INT);
+    });
+
+    let mir = mir_from_src(src, true);
+    let main = mir.main_body.unwrap();
+    assert_eq!(mir.bodies[main].name, Some("main".into()));
+
+    let mut out = vec![];
+    mir_interpreter::interpret(&mir, &mut out).unwrap();
+    assert_eq!(out, b"42\n");
+}
+
+// set `UPDATE_SNAPSHOTS` to write the current output as the expected one
+fn assert_snapshot(path: &str, actual: &str) {
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(path).unwrap();
+    assert!(expected == actual, "{path} does not match:\n{actual}");
+}
+
+#[test]
+fn brainfuck_snapshots() {
+    assert_snapshot(
+        "tests/snapshots/brainfuck.hir",
+        &hir_snapshot("examples/brainfuck.pty").unwrap(),
+    );
+    assert_snapshot(
+        "tests/snapshots/brainfuck.mir",
+        &mir_snapshot("examples/brainfuck.pty").unwrap(),
+    );
+}
+
+// optimizations assume `unreachable` is never reached and remove the branch leading to it,
+// so the failure only shows up without them
+#[test]
+#[should_panic = "internal error: entered unreachable code"]
+fn fail_unreachable() {
+    let args = test_args("tests/fail_unreachable.pty", false);
+    let mut out = vec![];
+    _ = compile(&args, &mut out);
+}
+
+#[test]
+fn assert_aborts_early() {
+    let args = test_args("tests/fail_assert_early.pty", true);
+    let mut out = vec![];
+    let Err(RuntimeError::Abort(msg)) = compile(&args, &mut out).unwrap().code else { panic!() };
+    assert!(msg.contains("assertion failed"));
+    assert_eq!(String::from_utf8(out).unwrap(), "before\n");
+}
+
+#[test]
+fn exit_code() {
+    let mut args = test_args("tests/exit_code.pty", true);
+    let mut out = vec![];
+    assert_eq!(compile(&args, &mut out).unwrap().code, Ok(2));
+    assert_eq!(out, b"exiting\n");
+
+    // a unit `main` exits successfully
+    args.path = "tests/functions.pty".into();
+    assert_eq!(compile(&args, &mut vec![]).unwrap().code, Ok(0));
+}
+
+#[test]
+fn type_errors_are_rendered() {
+    let errors = compile_test("tests/fail_variables.pty").unwrap_err();
+    let mut out = String::new();
+    miette::GraphicalReportHandler::new_themed(miette::GraphicalTheme::unicode_nocolor())
+        .render_report(&mut out, errors[0].as_ref())
+        .unwrap();
+    assert!(out.contains("tests/fail_variables.pty"), "{out}");
+    assert!(out.contains(r#"x = "1";"#), "{out}");
+    assert!(out.contains("expected `int`, found `str`"), "{out}");
+}
+
+#[test]
+fn diverging_results_are_unreachable() {
+    let src = "fn first(x: int) -> int {\n    return x;\n}\nfn main() { first(1); }";
+    let mir = mir_from_src(src, false);
+
+    // without optimizations the block after the `return` is kept, returning the `return` itself
+    let first = mir.bodies.iter().find(|body| body.name == Some("first".into())).unwrap();
+    let returns = (first.blocks.iter())
+        .filter_map(|block| match &block.terminator {
+            Terminator::Return(operand) => Some(operand),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert!(matches!(returns[..], [Operand::Place(_), Operand::Unreachable]), "{returns:?}");
+}
+
+#[test]
+fn no_mir_after_divergence() {
+    let src = "fn first(x: int) -> int {\n    return x;\n    println(\"dead\");\n    x + 1\n}\n\
+               fn main() { first(1); }";
+    let mir = mir_from_src(src, false);
+
+    let first = mir.bodies.iter().find(|body| body.name == Some("first".into())).unwrap();
+    assert!(first.blocks.iter().all(|block| block.statements.is_empty()), "{first:?}");
+    assert_eq!(first.blocks.len(), 2);
+}
+
+#[test]
+fn struct_name() {
+    let src = "struct Meters(value: int)\nfn main() { println(Meters(3)); }";
+    hir_from_src(src, |hir, tcx| {
+        // the constructor returns the struct
+        let ret = hir.root.iter().find_map(|&id| match &hir.exprs[id].kind {
+            hir::ExprKind::FnDecl(decl) if decl.ident.as_str() == "Meters" => Some(decl.ret),
+            _ => None,
+        });
+        let ret = ret.unwrap();
+        let TyKind::Struct { id, .. } = ret.0 else { panic!("{ret:?}") };
+        assert_eq!(tcx.struct_name(*id).as_str(), "Meters");
+        assert_eq!(tcx.display(ret).to_string(), "Meters");
+    });
+
+    let mir = mir_from_src(src, true);
+    let mut out = vec![];
+    mir_interpreter::interpret(&mir, &mut out).unwrap();
+    assert_eq!(out, b"Meters { value: 3 }\n");
+}
+
+// hand-built mir for testing the interpreter alone, `main` is the last body
+fn mir_with_main(bodies: impl IntoIterator<Item = Body>) -> Mir {
+    let mut mir = Mir::default();
+    for body in bodies {
+        mir.main_body = Some(mir.bodies.push(body));
+    }
+    mir
+}
+
+#[test]
+#[should_panic = "expected Int, found Bool(true)\n  at fn 0, block 1, statement 1"]
+fn interpreter_panics_with_location() {
+    // `true + 1` can never come out of lowering, so the interpreter reports where it happened
+    let mut body = Body::new(None, 0);
+    let local = body.new_local();
+    let statements = vec![
+        Statement::Assign { place: Place::local(local), rvalue: Constant::Int(1).into() },
+        Statement::Assign {
+            place: Place::local(local),
+            rvalue: RValue::Binary {
+                lhs: Constant::Bool(true).into(),
+                op: BinaryOp::IntAdd,
+                rhs: Operand::local(local),
+            },
+        },
+    ];
+    body.blocks.push(Block { statements: vec![], terminator: Terminator::Goto(1.into()) });
+    body.blocks.push(Block { statements, terminator: Terminator::Return(Operand::UNIT) });
+    _ = mir_interpreter::interpret(&mir_with_main([body]), &mut vec![]);
+}
+
+#[test]
+fn runtime_errors() {
+    let run = |src: &str, optimize: bool| {
+        mir_interpreter::interpret(&mir_from_src(src, optimize), &mut vec![])
+    };
+    for optimize in [true, false] {
+        let div = "fn main() { let zero = 0; println(1 / zero); }";
+        assert_eq!(run(div, optimize), Err(RuntimeError::DivideByZero));
+        let rem = "fn main() { let zero = 0; println(1 % zero); }";
+        assert_eq!(run(rem, optimize), Err(RuntimeError::DivideByZero));
+        let add = "fn main() { let max = 9223372036854775807; println(max + 1); }";
+        assert_eq!(run(add, optimize), Err(RuntimeError::Overflow));
+        let pop = "fn main() { let array: [int] = []; array.pop(); }";
+        assert_eq!(run(pop, optimize), Err(RuntimeError::PopEmptyArray));
+        let slice = r#"fn main() { let s = "abc"; println(s[1..5]); }"#;
+        let err = RuntimeError::SliceOutOfBounds { start: 1, end: 5, len: 3 };
+        assert_eq!(run(slice, optimize), Err(err));
+        let find = r#"fn main() { let s = "abc"; println(s.find("d")); }"#;
+        assert_eq!(run(find, optimize), Err(RuntimeError::SubstringNotFound));
+        let rfind = r#"fn main() { let s = "abc"; println(s.rfind("d")); }"#;
+        assert_eq!(run(rfind, optimize), Err(RuntimeError::SubstringNotFound));
+        let chr = "fn main() { let code = -1; println(code.chr()); }";
+        assert_eq!(run(chr, optimize), Err(RuntimeError::InvalidChar(-1)));
+
+        // indexing is bounds checked by the lowered code, which aborts with a rendered report
+        let index = "fn main() { let array = [1]; println(array[3]); }";
+        let Err(RuntimeError::Abort(msg)) = run(index, optimize) else { panic!() };
+        assert!(msg.contains("index out of bounds"), "{msg}");
+        let get = "fn main() { let m: Map<int, int> = map_new(); println(map_get(&m, 1)); }";
+        let Err(RuntimeError::Abort(msg)) = run(get, optimize) else { panic!() };
+        assert!(msg.contains("key not found"), "{msg}");
+    }
+}
+
+#[test]
+fn unchecked_index_out_of_bounds() {
+    // without a bounds check, the interpreter reports the index itself
+    let mut body = Body::new(None, 0);
+    let array = body.new_local();
+    let elem = body.new_local();
+    let statements = vec![
+        Statement::Assign {
+            place: Place::local(array),
+            rvalue: RValue::BuildArray(vec![(Constant::Int(1).into(), None)]),
+        },
+        Statement::Assign {
+            place: Place::local(elem),
+            rvalue: Operand::Place(Place {
+                local: array,
+                projections: vec![Projection::ConstantIndex(3)],
+            })
+            .into(),
+        },
+    ];
+    body.blocks.push(Block { statements, terminator: Terminator::Return(Operand::UNIT) });
+    let err = mir_interpreter::interpret(&mir_with_main([body]), &mut vec![]).unwrap_err();
+    assert_eq!(err, RuntimeError::IndexOutOfBounds { index: 3, len: 1 });
+}
+
+#[test]
+fn step_limit() {
+    let mir = mir_from_src("fn main() { let i = 0; loop { i += 1; } }", true);
+    let result = mir_interpreter::interpret_limited(&mir, &mut vec![], 10_000);
+    assert_eq!(result, Err(RuntimeError::StepLimitExceeded));
+
+    // a program that finishes within the limit is unaffected
+    let mir = compile_mir_test("tests/exit_code.pty").unwrap();
+    assert_eq!(mir_interpreter::interpret_limited(&mir, &mut vec![], 10_000), Ok(2));
+}
+
+#[test]
+fn trace_hook() {
+    // `main` calls a function returning 2, and returns its result
+    let mut callee = Body::new(None, 0);
+    callee.blocks.push(Block {
+        statements: vec![],
+        terminator: Terminator::Return(Constant::Int(2).into()),
+    });
+    let mut main = Body::new(None, 0);
+    let result = main.new_local();
+    let call = RValue::Call { function: Constant::Func(0.into()).into(), args: thin_vec![] };
+    let statements = vec![Statement::Assign { place: Place::local(result), rvalue: call }];
+    main.blocks.push(Block { statements, terminator: Terminator::Goto(1.into()) });
+    main.blocks
+        .push(Block { statements: vec![], terminator: Terminator::Return(Operand::local(result)) });
+    let mir = mir_with_main([callee, main]);
+
+    let mut trace = vec![];
+    let mut hook = |body: BodyId, block: BlockId, step: Step| {
+        let step = match step {
+            Step::Statement(Statement::Assign { place, .. }) => format!("assign {place}"),
+            Step::Terminator(Terminator::Goto(_)) => "goto".into(),
+            Step::Terminator(Terminator::Return(_)) => "return".into(),
+            Step::Terminator(other) => panic!("{other:?}"),
+        };
+        trace.push((body.index(), block.index(), step));
+    };
+    assert_eq!(mir_interpreter::interpret_traced(&mir, &mut vec![], &mut hook), Ok(2));
+    assert_eq!(
+        trace,
+        [
+            (1, 0, "assign var 0".to_string()),
+            (0, 0, "return".to_string()),
+            (1, 0, "goto".to_string()),
+            (1, 1, "return".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn byte_arithmetic_wraps() {
+    let byte =
+        |lhs, op, rhs| mir_interpreter::binary_op(Value::Byte(lhs), op, Value::Byte(rhs)).unwrap();
+    assert_eq!(byte(255, BinaryOp::ByteAdd, 1), Value::Byte(0));
+    assert_eq!(byte(250, BinaryOp::ByteAdd, 10), Value::Byte(4));
+    assert_eq!(byte(254, BinaryOp::ByteAdd, 1), Value::Byte(255));
+    assert_eq!(byte(0, BinaryOp::ByteSub, 1), Value::Byte(255));
+    assert_eq!(byte(3, BinaryOp::ByteSub, 5), Value::Byte(254));
+    assert_eq!(byte(255, BinaryOp::ByteEq, 255), Value::Bool(true));
+    assert_eq!(byte(255, BinaryOp::ByteNeq, 0), Value::Bool(true));
 }
@@ -2,8 +2,8 @@ use std::fmt;
 
 use thin_vec::ThinVec;
 
-use super::{Function, GenericId, GenericRange, StructId, Ty, TyCtx, TyVid};
-use crate::symbol::Symbol;
+use super::{Function, GenericId, StructId, Ty, TyCtx, TyVid};
+use crate::ast::Mutability;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum TyKind<'tcx> {
@@ -14,17 +14,17 @@ pub enum TyKind<'tcx> {
     Char,
     Str,
     Range,
+    CharRange,
     Array(Ty<'tcx>),
+    Map(Ty<'tcx>, Ty<'tcx>),
+    Set(Ty<'tcx>),
     Function(Function<'tcx>),
-    Struct {
-        id: StructId,
-        generics: GenericRange,
-        symbols: ThinVec<Symbol>,
-        fields: ThinVec<Ty<'tcx>>,
-    },
+    Tuple(ThinVec<Ty<'tcx>>),
+    // the fields are looked up through `TyCtx::struct_fields`, so a struct can refer to itself
+    Struct { id: StructId, generics: ThinVec<Ty<'tcx>> },
     Generic(GenericId),
     Infer(TyVid),
-    Ref(Ty<'tcx>),
+    Ref(Ty<'tcx>, Mutability),
     Poison,
 }
 
@@ -32,11 +32,14 @@ impl<'tcx> Ty<'tcx> {
     pub fn generics(self, f: &mut impl FnMut(GenericId)) {
         match *self.0 {
             TyKind::Generic(id) => f(id),
-            TyKind::Array(ty) | TyKind::Ref(ty) => ty.generics(f),
+            TyKind::Array(ty) | TyKind::Set(ty) | TyKind::Ref(ty, _) => ty.generics(f),
+            TyKind::Map(key, value) => {
+                key.generics(f);
+                value.generics(f);
+            }
             TyKind::Function(ref func) => func.generics(f),
-            TyKind::Struct { ref fields, .. } => {
-                // this seems wrong.
-                fields.iter().for_each(|field| field.generics(f));
+            TyKind::Tuple(ref elems) | TyKind::Struct { generics: ref elems, .. } => {
+                elems.iter().for_each(|elem| elem.generics(f));
             }
             TyKind::Poison
             | TyKind::Infer(..)
@@ -46,10 +49,36 @@ impl<'tcx> Ty<'tcx> {
             | TyKind::Int
             | TyKind::Never
             | TyKind::Range
+            | TyKind::CharRange
             | TyKind::Str => {}
         }
     }
 
+    // whether any inference variable remains within `ty`, which must never reach lowering
+    pub fn has_infer(self) -> bool {
+        match *self.0 {
+            TyKind::Infer(..) => true,
+            TyKind::Array(ty) | TyKind::Set(ty) | TyKind::Ref(ty, _) => ty.has_infer(),
+            TyKind::Map(key, value) => key.has_infer() || value.has_infer(),
+            TyKind::Function(Function { ref params, ret }) => {
+                params.iter().any(|param| param.has_infer()) || ret.has_infer()
+            }
+            TyKind::Tuple(ref tys) | TyKind::Struct { generics: ref tys, .. } => {
+                tys.iter().any(|ty| ty.has_infer())
+            }
+            TyKind::Poison
+            | TyKind::Generic(_)
+            | TyKind::Unit
+            | TyKind::Bool
+            | TyKind::Char
+            | TyKind::Int
+            | TyKind::Never
+            | TyKind::Range
+            | TyKind::CharRange
+            | TyKind::Str => false,
+        }
+    }
+
     pub fn replace_generics(
         self,
         tcx: &'tcx TyCtx<'tcx>,
@@ -57,16 +86,24 @@ impl<'tcx> Ty<'tcx> {
     ) -> Ty<'tcx> {
         match *self.0 {
             TyKind::Generic(id) => f(id),
-            TyKind::Ref(ty) => tcx.intern(TyKind::Ref(ty.replace_generics(tcx, f))),
+            TyKind::Ref(ty, mutability) => {
+                tcx.intern(TyKind::Ref(ty.replace_generics(tcx, f), mutability))
+            }
             TyKind::Array(ty) => tcx.intern(TyKind::Array(ty.replace_generics(tcx, f))),
+            TyKind::Set(ty) => tcx.intern(TyKind::Set(ty.replace_generics(tcx, f))),
+            TyKind::Map(key, value) => tcx
+                .intern(TyKind::Map(key.replace_generics(tcx, f), value.replace_generics(tcx, f))),
             TyKind::Function(Function { ref params, ret, .. }) => {
                 let params = params.iter().map(|param| param.replace_generics(tcx, f)).collect();
                 let ret = ret.replace_generics(tcx, f);
                 tcx.intern(TyKind::Function(Function { params, ret }))
             }
-            TyKind::Struct { ref fields, generics, ref symbols, id } => {
-                let fields = fields.iter().map(|field| field.replace_generics(tcx, f)).collect();
-                tcx.intern(TyKind::Struct { id, generics, symbols: symbols.clone(), fields })
+            TyKind::Tuple(ref elems) => tcx.intern(TyKind::Tuple(
+                elems.iter().map(|elem| elem.replace_generics(tcx, f)).collect(),
+            )),
+            TyKind::Struct { id, ref generics } => {
+                let generics = generics.iter().map(|ty| ty.replace_generics(tcx, f)).collect();
+                tcx.intern(TyKind::Struct { id, generics })
             }
             TyKind::Infer(..) => unreachable!(),
             TyKind::Poison
@@ -76,6 +113,7 @@ impl<'tcx> Ty<'tcx> {
             | TyKind::Int
             | TyKind::Never
             | TyKind::Range
+            | TyKind::CharRange
             | TyKind::Str => self,
         }
     }
@@ -126,8 +164,15 @@ impl TyCtx<'_> {
                     TyKind::Unit => write!(f, "()"),
                     TyKind::Never => write!(f, "!"),
                     TyKind::Range => write!(f, "Range"),
+                    TyKind::CharRange => write!(f, "Range<char>"),
                     TyKind::Array(of) => write!(f, "[{}]", tcx.display(*of)),
-                    TyKind::Ref(of) => write!(f, "&{}", tcx.display(*of)),
+                    TyKind::Map(key, value) => {
+                        write!(f, "Map<{}, {}>", tcx.display(*key), tcx.display(*value))
+                    }
+                    TyKind::Set(of) => write!(f, "Set<{}>", tcx.display(*of)),
+                    TyKind::Ref(of, mutability) => {
+                        write!(f, "{}{}", mutability.prefix(), tcx.display(*of))
+                    }
                     TyKind::Function(Function { params, ret }) => {
                         write!(f, "fn(")?;
                         for (i, param) in params.iter().enumerate() {
@@ -137,14 +182,25 @@ impl TyCtx<'_> {
                         write!(f, ")")?;
                         write!(f, " -> {}", tcx.display(*ret))
                     }
+                    TyKind::Tuple(elems) => {
+                        write!(f, "(")?;
+                        for (i, elem) in elems.iter().enumerate() {
+                            let prefix = if i == 0 { "" } else { ", " };
+                            write!(f, "{prefix}{}", tcx.display(*elem))?;
+                        }
+                        write!(f, ")")
+                    }
                     TyKind::Infer(_) => write!(f, "_"),
                     TyKind::Generic(id) => write!(f, "{}", tcx.generic_symbol(*id)),
-                    TyKind::Struct { id, fields, .. } => {
+                    TyKind::Struct { id, generics } => {
                         write!(f, "{}", tcx.struct_name(*id))?;
+                        if generics.is_empty() {
+                            return Ok(());
+                        }
                         write!(f, "<")?;
-                        for (i, field) in fields.iter().enumerate() {
+                        for (i, generic) in generics.iter().enumerate() {
                             let sep = if i != 0 { ", " } else { "" };
-                            write!(f, "{sep}{}", tcx.display(*field))?;
+                            write!(f, "{sep}{}", tcx.display(*generic))?;
                         }
                         write!(f, ">")
                     }
@@ -2,7 +2,7 @@ mod generic_range;
 mod interned;
 mod kind;
 
-use std::{cell::RefCell, cmp::Ordering, collections::BTreeMap, hash::Hash};
+use std::{cell::RefCell, cmp::Ordering, hash::Hash};
 
 pub use generic_range::GenericRange;
 use index_vec::IndexVec;
@@ -13,7 +13,12 @@ use thin_vec::ThinVec;
 
 type TyInterner<'tcx> = &'tcx Interner<TyKind<'tcx>>;
 
-use crate::{HashMap, ast::Identifier, define_id, symbol::Symbol};
+use crate::{
+    HashMap,
+    ast::{Generic, Mutability},
+    define_id,
+    symbol::Symbol,
+};
 
 pub type Ty<'tcx> = Interned<'tcx, TyKind<'tcx>>;
 
@@ -24,6 +29,7 @@ static INT: TyKind = TyKind::Int;
 static CHAR: TyKind = TyKind::Char;
 static STR: TyKind = TyKind::Str;
 static RANGE: TyKind = TyKind::Range;
+static CHAR_RANGE: TyKind = TyKind::CharRange;
 static POISON: TyKind = TyKind::Poison;
 
 impl Ty<'_> {
@@ -34,6 +40,7 @@ impl Ty<'_> {
     pub const CHAR: Self = Self(&CHAR);
     pub const STR: Self = Self(&STR);
     pub const RANGE: Self = Self(&RANGE);
+    pub const CHAR_RANGE: Self = Self(&CHAR_RANGE);
     pub const POISON: Self = Self(&POISON);
 }
 
@@ -49,7 +56,14 @@ pub struct Function<'tcx> {
 
 impl<'tcx> Function<'tcx> {
     pub fn caller(&self, tcx: &'tcx TyCtx<'tcx>) -> Self {
-        let mut map = HashMap::default();
+        self.caller_with(tcx, &mut HashMap::default())
+    }
+    // like `caller`, but also returns the inference variable chosen for each generic
+    pub fn caller_with(
+        &self,
+        tcx: &'tcx TyCtx<'tcx>,
+        map: &mut HashMap<GenericId, Ty<'tcx>>,
+    ) -> Self {
         self.generics(&mut |id| _ = map.entry(id).or_insert_with(|| tcx.new_infer()));
         let f = |id| map[&id];
         let params = self.params.iter().map(|param| param.replace_generics(tcx, f)).collect();
@@ -72,25 +86,35 @@ impl<'tcx> TyCtx<'tcx> {
     pub fn new(interner: TyInterner<'tcx>) -> Self {
         Self { inner: RefCell::default(), interner }
     }
-    pub fn new_generics(&self, generics: &[Identifier]) -> GenericRange {
+    pub fn new_generics(&self, generics: &[Generic]) -> GenericRange {
         let mut inner = self.inner.borrow_mut();
         let mut iter = generics.iter();
         let Some(start) = iter.next() else { return GenericRange::EMPTY };
-        let start = inner.new_generic(start.symbol);
-        iter.for_each(|generic| _ = inner.new_generic(generic.symbol));
+        let start = inner.new_generic(start.ident.symbol);
+        iter.for_each(|generic| _ = inner.new_generic(generic.ident.symbol));
         GenericRange { start, len: generics.len().try_into().unwrap() }
     }
     pub fn generic_symbol(&self, id: GenericId) -> Symbol {
         self.inner.borrow_mut().generic_names[id]
     }
+    // the struct's fields are set separately, so that they can refer back to the struct
     pub fn new_struct(
         &self,
         name: Symbol,
         generics: GenericRange,
         symbols: ThinVec<Symbol>,
-        fields: ThinVec<Ty<'tcx>>,
     ) -> Ty<'tcx> {
-        self.intern(self.inner.borrow_mut().new_struct(name, generics, symbols, fields))
+        let id = self.inner.borrow_mut().structs.push(StructDef {
+            name,
+            generics,
+            symbols,
+            fields: vec![],
+        });
+        let generics = generics.iter().map(|generic| self.intern(TyKind::Generic(generic)));
+        self.intern(TyKind::Struct { id, generics: generics.collect() })
+    }
+    pub fn set_struct_fields(&self, id: StructId, fields: &[Ty<'tcx>]) {
+        self.inner.borrow_mut().structs[id].fields = fields.to_vec();
     }
     pub fn add_method(&self, ty: Ty<'tcx>, name: Symbol, func: Function<'tcx>) {
         let func = self.intern(TyKind::Function(func));
@@ -101,8 +125,30 @@ impl<'tcx> TyCtx<'tcx> {
         let TyKind::Function(func) = ty.0 else { unreachable!() };
         Some(func)
     }
+    pub fn add_const(&self, ty: Ty<'tcx>, name: Symbol, const_ty: Ty<'tcx>) {
+        self.inner.borrow_mut().consts.insert(ty, name, const_ty);
+    }
+    pub fn get_const(&self, ty: Ty<'tcx>, name: Symbol) -> Option<Ty<'tcx>> {
+        self.inner.borrow().consts.get(ty, name)
+    }
     pub fn struct_name(&self, id: StructId) -> Symbol {
-        self.inner.borrow().struct_names[id]
+        self.inner.borrow().structs[id].name
+    }
+    pub fn struct_symbols(&self, id: StructId) -> ThinVec<Symbol> {
+        self.inner.borrow().structs[id].symbols.clone()
+    }
+    // the types of the fields of a struct, with its generics replaced by those of `ty`
+    pub fn struct_fields(&'tcx self, ty: Ty<'tcx>) -> ThinVec<Ty<'tcx>> {
+        let TyKind::Struct { id, ref generics } = *ty.0 else { unreachable!("{ty:?}") };
+        let (declared, fields) = {
+            let def = &self.inner.borrow().structs[id];
+            (def.generics, def.fields.clone())
+        };
+        let replace = |generic| match declared.iter().position(|decl| decl == generic) {
+            Some(i) => generics[i],
+            None => self.intern(TyKind::Generic(generic)),
+        };
+        fields.iter().map(|field| field.replace_generics(self, replace)).collect()
     }
     pub fn intern(&self, kind: TyKind<'tcx>) -> Ty<'tcx> {
         #[cfg(debug_assertions)]
@@ -146,9 +192,20 @@ impl<'tcx> TyCtx<'tcx> {
 #[derive(Default, Debug)]
 struct TyCtxInner<'tcx> {
     subs: IndexVec<TyVid, Ty<'tcx>>,
-    struct_names: IndexVec<StructId, Symbol>,
+    structs: IndexVec<StructId, StructDef<'tcx>>,
     generic_names: IndexVec<GenericId, Symbol>,
-    methods: BTreeMap<(TyKey<'tcx>, Symbol), Ty<'tcx>>,
+    methods: MethodMap<'tcx, Ty<'tcx>>,
+    // associated constants, accessed through their type like `Point.ORIGIN`
+    consts: MethodMap<'tcx, Ty<'tcx>>,
+}
+
+#[derive(Debug)]
+struct StructDef<'tcx> {
+    name: Symbol,
+    generics: GenericRange,
+    symbols: ThinVec<Symbol>,
+    // the field types as declared, before any generics are replaced
+    fields: Vec<Ty<'tcx>>,
 }
 
 #[derive(Debug)]
@@ -161,20 +218,35 @@ impl PartialOrd for TyKey<'_> {
 }
 
 impl Ord for TyKey<'_> {
-    #[expect(clippy::match_same_arms)]
     fn cmp(&self, other: &Self) -> Ordering {
         use TyKind as T;
         match (self.0.0, other.0.0) {
             (T::Generic(_), _) | (_, T::Generic(_)) => Ordering::Equal,
-            (&T::Array(lhs), &T::Array(rhs)) => TyKey(lhs).cmp(&TyKey(rhs)),
-            (&T::Ref(lhs), &T::Ref(rhs)) => TyKey(lhs).cmp(&TyKey(rhs)),
-            (&T::Ref(ref_), _) => TyKey(ref_).cmp(&TyKey(other.0)),
-            (_, &T::Ref(ref_)) => TyKey(self.0).cmp(&TyKey(ref_)),
+            (&T::Array(lhs), &T::Array(rhs)) | (&T::Set(lhs), &T::Set(rhs)) => {
+                TyKey(lhs).cmp(&TyKey(rhs))
+            }
+            (&T::Map(lhs_key, lhs_value), &T::Map(rhs_key, rhs_value)) => {
+                cmp_keys(&[lhs_key, lhs_value], &[rhs_key, rhs_value])
+            }
+            (T::Tuple(lhs), T::Tuple(rhs)) => cmp_keys(lhs, rhs),
+            (T::Struct { id: lhs_id, generics: lhs }, T::Struct { id: rhs_id, generics: rhs }) => {
+                lhs_id.cmp(rhs_id).then_with(|| cmp_keys(lhs, rhs))
+            }
+            (&T::Ref(lhs, _), &T::Ref(rhs, _)) => TyKey(lhs).cmp(&TyKey(rhs)),
+            (&T::Ref(ref_, _), _) => TyKey(ref_).cmp(&TyKey(other.0)),
+            (_, &T::Ref(ref_, _)) => TyKey(self.0).cmp(&TyKey(ref_)),
             _ => self.0.cmp(&other.0),
         }
     }
 }
 
+// compares element-wise, so generics nested in tuples and structs also match any type
+fn cmp_keys(lhs: &[Ty], rhs: &[Ty]) -> Ordering {
+    let mut elems = lhs.iter().zip(rhs).map(|(&lhs, &rhs)| TyKey(lhs).cmp(&TyKey(rhs)));
+    let elems = elems.find(|ordering| ordering.is_ne()).unwrap_or(Ordering::Equal);
+    lhs.len().cmp(&rhs.len()).then(elems)
+}
+
 impl PartialEq for TyKey<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.cmp(other).is_eq()
@@ -183,23 +255,41 @@ impl PartialEq for TyKey<'_> {
 
 impl Eq for TyKey<'_> {}
 
+/// Values defined for a type under a name, like methods.
+/// Generics within a `TyKey` match any type, so it has no total order to search a sorted map by.
+#[derive(Debug)]
+pub struct MethodMap<'tcx, T> {
+    entries: HashMap<Symbol, Vec<(Ty<'tcx>, T)>>,
+}
+
+impl<T> Default for MethodMap<'_, T> {
+    fn default() -> Self {
+        Self { entries: HashMap::default() }
+    }
+}
+
+impl<'tcx, T: Copy> MethodMap<'tcx, T> {
+    pub fn insert(&mut self, ty: Ty<'tcx>, name: Symbol, value: T) {
+        let entries = self.entries.entry(name).or_default();
+        match entries.iter_mut().find(|(key, _)| *key == ty) {
+            Some(entry) => entry.1 = value,
+            None => entries.push((ty, value)),
+        }
+    }
+    // an exact match is preferred over one defined for a generic type
+    pub fn get(&self, ty: Ty<'tcx>, name: Symbol) -> Option<T> {
+        let entries = self.entries.get(&name)?;
+        let exact = entries.iter().find(|(key, _)| *key == ty);
+        exact.or_else(|| entries.iter().find(|(key, _)| TyKey(*key) == TyKey(ty))).map(|e| e.1)
+    }
+}
+
 impl<'tcx> TyCtxInner<'tcx> {
     fn add_method(&mut self, ty: Ty<'tcx>, name: Symbol, method: Ty<'tcx>) {
-        self.methods.insert((TyKey(ty), name), method);
+        self.methods.insert(ty, name, method);
     }
     fn get_method(&self, ty: Ty<'tcx>, name: Symbol) -> Option<Ty<'tcx>> {
-        self.methods.get(&(TyKey(ty), name)).copied()
-    }
-
-    fn new_struct(
-        &mut self,
-        name: Symbol,
-        generics: GenericRange,
-        symbols: ThinVec<Symbol>,
-        fields: ThinVec<Ty<'tcx>>,
-    ) -> TyKind<'tcx> {
-        let id = self.struct_names.push(name);
-        TyKind::Struct { id, generics, symbols, fields }
+        self.methods.get(ty, name)
     }
 
     fn new_generic(&mut self, symbol: Symbol) -> GenericId {
@@ -233,8 +323,17 @@ impl<'tcx> TyCtxInner<'tcx> {
             TyKind::Array(of) => {
                 intern!(TyKind::Array(self.try_infer_deep(*of, intern).map_err(|_| ty)?))
             }
-            TyKind::Ref(of) => {
-                intern!(TyKind::Ref(self.try_infer_deep(*of, intern).map_err(|_| ty)?))
+            TyKind::Set(of) => {
+                intern!(TyKind::Set(self.try_infer_deep(*of, intern).map_err(|_| ty)?))
+            }
+            TyKind::Ref(of, mutability) => {
+                let of = self.try_infer_deep(*of, intern).map_err(|_| ty)?;
+                intern!(TyKind::Ref(of, *mutability))
+            }
+            TyKind::Map(key, value) => {
+                let key = self.try_infer_deep(*key, intern).map_err(|_| ty)?;
+                let value = self.try_infer_deep(*value, intern).map_err(|_| ty)?;
+                intern!(TyKind::Map(key, value))
             }
             TyKind::Function(Function { params, ret }) => {
                 let params = params
@@ -245,23 +344,25 @@ impl<'tcx> TyCtxInner<'tcx> {
                 let ret = self.try_infer_deep(*ret, intern)?;
                 intern!(TyKind::Function(Function { params, ret }))
             }
-            TyKind::Struct { id, generics, symbols, fields } => {
-                let fields = fields
+            TyKind::Tuple(elems) => {
+                let elems = elems
                     .iter()
-                    .map(|field| self.try_infer_deep(*field, intern))
+                    .map(|elem| self.try_infer_deep(*elem, intern))
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| ty)?;
+                intern!(TyKind::Tuple(elems))
+            }
+            TyKind::Struct { id, generics } => {
+                let generics = generics
+                    .iter()
+                    .map(|generic| self.try_infer_deep(*generic, intern))
                     .collect::<Result<_, _>>()?;
-                intern!(TyKind::Struct {
-                    id: *id,
-                    generics: *generics,
-                    symbols: symbols.clone(),
-                    fields
-                })
+                intern!(TyKind::Struct { id: *id, generics })
             }
             _ => inferred,
         })
     }
 
-    #[expect(clippy::match_same_arms)]
     fn eq(&mut self, lhs: Ty<'tcx>, rhs: Ty<'tcx>) -> Result<(), [Ty<'tcx>; 2]> {
         match (lhs.0, rhs.0) {
             (lhs, rhs) if lhs == rhs => Ok(()),
@@ -269,22 +370,36 @@ impl<'tcx> TyCtxInner<'tcx> {
             (TyKind::Infer(l), TyKind::Infer(r)) if l == r => Ok(()),
             (TyKind::Infer(var), _) => self.insertl(*var, rhs),
             (_, TyKind::Infer(var)) => self.insertr(lhs, *var),
-            (TyKind::Array(lhs), TyKind::Array(rhs)) => self.eq(*lhs, *rhs),
-            (TyKind::Ref(lhs), TyKind::Ref(rhs)) => self.eq(*lhs, *rhs),
-            (TyKind::Function(lhs), TyKind::Function(rhs)) => {
-                assert_eq!(lhs.params.len(), rhs.params.len());
-                lhs.params.iter().zip(&rhs.params).try_for_each(|(l, r)| self.eq(*l, *r))?;
-                self.eq(lhs.ret, rhs.ret)
+            (TyKind::Array(lhs), TyKind::Array(rhs)) | (TyKind::Set(lhs), TyKind::Set(rhs)) => {
+                self.eq(*lhs, *rhs)
+            }
+            (TyKind::Map(lkey, lvalue), TyKind::Map(rkey, rvalue)) => {
+                self.eq(*lkey, *rkey)?;
+                self.eq(*lvalue, *rvalue)
+            }
+            (TyKind::Ref(lhs, lm), TyKind::Ref(rhs, rm)) if lm == rm => self.eq(*lhs, *rhs),
+            (TyKind::Function(lfn), TyKind::Function(rfn)) => {
+                if lfn.params.len() != rfn.params.len() {
+                    return Err([lhs, rhs]);
+                }
+                lfn.params.iter().zip(&rfn.params).try_for_each(|(l, r)| self.eq(*l, *r))?;
+                self.eq(lfn.ret, rfn.ret)
+            }
+            (TyKind::Tuple(lelems), TyKind::Tuple(relems)) => {
+                if lelems.len() != relems.len() {
+                    return Err([lhs, rhs]);
+                }
+                lelems.iter().zip(relems).try_for_each(|(l, r)| self.eq(*l, *r))
             }
             (
-                TyKind::Struct { id: lid, fields: lfields, .. },
-                TyKind::Struct { id: rid, fields: rfields, .. },
+                TyKind::Struct { id: lid, generics: lgenerics },
+                TyKind::Struct { id: rid, generics: rgenerics },
             ) => {
                 if lid != rid {
                     return Err([lhs, rhs]);
                 }
-                debug_assert_eq!(lfields.len(), rfields.len());
-                for (lhs, rhs) in lfields.iter().zip(rfields) {
+                debug_assert_eq!(lgenerics.len(), rgenerics.len());
+                for (lhs, rhs) in lgenerics.iter().zip(rgenerics) {
                     self.eq(*lhs, *rhs)?;
                 }
                 Ok(())
@@ -294,8 +409,14 @@ impl<'tcx> TyCtxInner<'tcx> {
     }
 
     /// Says that `lhs` must be a subtype of `rhs`.
-    /// never is a subtype of everything.
+    /// never is a subtype of everything, and `&mut T` is a subtype of `&T`.
     fn sub(&mut self, lhs: Ty<'tcx>, rhs: Ty<'tcx>) -> Result<(), [Ty<'tcx>; 2]> {
+        let shallow = |ty| self.try_infer_shallow(ty).map(|ty| ty.0);
+        if let (Ok(&TyKind::Ref(lhs, Mutability::Mut)), Ok(&TyKind::Ref(rhs, Mutability::Not))) =
+            (shallow(lhs), shallow(rhs))
+        {
+            return self.eq(lhs, rhs);
+        }
         let Err([lhs, rhs]) = self.eq(lhs, rhs) else { return Ok(()) };
         if lhs.is_never() { Ok(()) } else { Err([lhs, rhs]) }
     }
@@ -345,7 +466,7 @@ impl<'tcx> TyCtxInner<'tcx> {
 impl Ty<'_> {
     pub fn fully_deref(self) -> Self {
         let mut ty = self;
-        while let TyKind::Ref(of) = ty.0 {
+        while let TyKind::Ref(of, _) = ty.0 {
             ty = *of;
         }
         ty
@@ -353,7 +474,7 @@ impl Ty<'_> {
     pub fn ref_depth(self) -> usize {
         let mut depth = 0;
         let mut ty = self;
-        while let TyKind::Ref(of) = ty.0 {
+        while let TyKind::Ref(of, _) = ty.0 {
             ty = *of;
             depth += 1;
         }